@@ -0,0 +1,126 @@
+//! Cost of building and laying out the grid on every `view`: one container and two text
+//! widgets per cell, as the grid used to be drawn, against the cached canvas.
+
+use std::collections::BTreeMap;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use iced::advanced::layout::Limits;
+use iced::advanced::widget::Tree;
+use iced::widget::canvas::{Cache, Frame};
+use iced::widget::{column, container, mouse_area, text, Column, Row};
+use iced::{Background, Border, Element, Font, Length, Pixels, Renderer, Size, Theme};
+
+use cross_iced::api_types::Pos;
+use cross_iced::msg::Msg;
+use cross_iced::import_puz::{ImportedPuz, PuzStrings};
+use cross_iced::palette::Palette;
+use cross_iced::state::{Board, Cell};
+use cross_iced::view::GridCanvas;
+
+const CELL_SIZE: f32 = 30.;
+
+/// A `size` x `size` grid with a block every few cells, half filled in
+fn board(size: usize) -> Board {
+    let solution: Vec<Vec<char>> = (0..size)
+        .map(|r_idx| (0..size).map(|c_idx| {
+            if r_idx % 4 == 3 && c_idx % 4 == 3 {
+                '.'
+            } else {
+                (b'A' + ((r_idx + c_idx) % 26) as u8) as char
+            }
+        }).collect())
+        .collect();
+    let player_state = solution.iter().enumerate()
+        .map(|(r_idx, row)| row.iter().map(|ch| if *ch != '.' && r_idx % 2 == 1 { '-' } else { *ch }).collect())
+        .collect();
+
+    let strings = PuzStrings::new("Bench".to_string(), String::new(), String::new(), String::new());
+    let imported_puz = ImportedPuz::from_grid(solution, player_state, strings, &BTreeMap::new(), Vec::new());
+    Board::from_puzzle("Bench".to_string(), &imported_puz)
+}
+
+fn renderer() -> Renderer {
+    Renderer::TinySkia(iced_tiny_skia::Renderer::new(iced_tiny_skia::Backend::new(), Font::default(), Pixels(16.)))
+}
+
+/// The grid as it was drawn before the canvas
+fn container_grid(board: &Board, palette: Palette) -> Element<'_, Msg> {
+    let rows: Vec<Element<Msg>> = board.content.iter().enumerate()
+        .map(|(r_idx, content_row)| {
+            let cells: Vec<Element<Msg>> = content_row.iter().enumerate()
+                .map(|(c_idx, tile)| {
+                    let pos = Pos::new(r_idx, c_idx);
+                    let background = match tile {
+                        Cell::Black => palette.black,
+                        _ if pos == board.cur_pos => palette.cursor,
+                        _ if board.cur_sel.contains(&pos) => palette.word,
+                        _ => palette.background,
+                    };
+                    let ch = match tile {
+                        Cell::OccupiedRight(ch) | Cell::OccupiedWrong(ch) | Cell::Pencil(ch) => *ch,
+                        Cell::Empty | Cell::Black => ' ',
+                    };
+                    let number = board.number_at(&pos).map(|num| num.to_string()).unwrap_or_default();
+                    let content = column![
+                        text(number).size(CELL_SIZE * 0.25).line_height(1.0).width(Length::Fill),
+                        text(ch.to_string()).size(CELL_SIZE * 0.6).line_height(1.0).width(Length::Fill),
+                    ].padding([0, 0, 0, 2]);
+
+                    let cell = container(content)
+                        .width(CELL_SIZE)
+                        .height(CELL_SIZE)
+                        .style(move |_th: &Theme| container::Appearance {
+                            background: Some(Background::Color(background)),
+                            border: Border { color: palette.grid_lines, radius: 0.into(), width: 1.0 },
+                            ..Default::default()
+                        });
+                    mouse_area(cell).on_press(Msg::ClickCell(pos)).into()
+                })
+                .collect();
+            Row::from_vec(cells).into()
+        })
+        .collect();
+
+    Column::from_vec(rows).into()
+}
+
+fn view_and_layout(element: Element<'_, Msg>, renderer: &Renderer) {
+    let mut tree = Tree::new(&element);
+    let node = element.as_widget().layout(&mut tree, renderer, &Limits::new(Size::ZERO, Size::INFINITY));
+    criterion::black_box(node);
+}
+
+fn grid_view(c: &mut Criterion) {
+    let renderer = renderer();
+    let palette = Palette::LIGHT;
+
+    let mut group = c.benchmark_group("grid_view");
+    for size in [15, 21, 45] {
+        let board = board(size);
+        let cache = Cache::new();
+        let grid = || GridCanvas { board: &board, palette, cell_size: CELL_SIZE, cache: &cache };
+        let grid_size = Size::new(CELL_SIZE * size as f32, CELL_SIZE * size as f32);
+
+        group.bench_with_input(BenchmarkId::new("containers", size), &size, |b, _| {
+            b.iter(|| view_and_layout(container_grid(&board, palette), &renderer))
+        });
+        group.bench_with_input(BenchmarkId::new("canvas", size), &size, |b, _| {
+            b.iter(|| view_and_layout(grid().view(), &renderer))
+        });
+        // what a change to the board costs on the next redraw
+        group.bench_with_input(BenchmarkId::new("canvas_geometry", size), &size, |b, _| {
+            b.iter(|| {
+                let mut frame = Frame::new(&renderer, grid_size);
+                grid().draw_grid(&mut frame);
+                frame.into_geometry()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("canvas_cached", size), &size, |b, _| {
+            b.iter(|| cache.draw(&renderer, grid_size, |frame| grid().draw_grid(frame)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, grid_view);
+criterion_main!(benches);
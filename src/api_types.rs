@@ -1,4 +1,3 @@
-
 #[derive(Debug, Clone, Copy)]
 pub enum Direction {
     Up,
@@ -20,6 +19,7 @@ impl Direction {
 
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypingDir {
     Across,
     Down,
@@ -34,17 +34,18 @@ impl TypingDir {
     }
 }
 
-impl Into<Direction> for TypingDir {
-    fn into(self) -> Direction {
-        match self {
-            Self::Across => Direction::Right,
-            Self::Down => Direction::Down
+impl From<TypingDir> for Direction {
+    fn from(dir: TypingDir) -> Self {
+        match dir {
+            TypingDir::Across => Direction::Right,
+            TypingDir::Down => Direction::Down
         }
     }
 }
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos {
     pub row: usize,
     pub col: usize
@@ -74,11 +75,3 @@ impl DeltaPos {
         DeltaPos{row, col}
     }
 }
-
-#[derive(Debug, Clone, Copy)]
-pub enum Msg {
-    MoveCursor(Direction),
-    TypeLetter(char),
-    ClearCell,
-    ToggleTypingDir,
-}
\ No newline at end of file
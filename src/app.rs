@@ -1,243 +1,821 @@
-use std::fs::File;
-use std::io::BufReader;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use iced::alignment::Horizontal;
-use iced::keyboard::key::Named;
-use iced::keyboard::{on_key_release, Key, Modifiers};
+use iced::keyboard::on_key_release;
 
-use iced::widget::vertical_space;
-use iced::Border;
+use iced::widget::scrollable::RelativeOffset;
+use iced::widget::{canvas, scrollable, vertical_space};
+use iced::theme;
 use iced::{
     executor,
-    widget::{container, text, Column, Row, row, column},
-    Application, Color, Command, Element, Theme,
+    widget::{button, checkbox, container, mouse_area, pick_list, progress_bar, text, text_input, Column, row, column},
+    event, window, Alignment, Application, Command, Element, Event, Length, Size, Subscription, Theme,
 };
 
-use crate::import_puz;
+use crate::export_puz::write_puzzle;
+use crate::config::Config;
 use crate::import_puz::ImportedPuz;
+use crate::library::{scan_library, sort_entries, LibraryEntry, LibrarySort};
+use crate::loader::{self, ArchiveEntry};
+use crate::palette::Palette;
+use crate::session::{puzzle_hash, Session};
 use crate::state::*;
-use crate::api_types::{Pos, Msg, Direction};
+use crate::view::GridCanvas;
+use crate::api_types::{Pos, TypingDir};
+use crate::msg::Msg;
+
+const MIN_CELL_SIZE: f32 = 16.;
+const MAX_CELL_SIZE: f32 = 100.;
+const ZOOM_STEP: f32 = 1.2;
+const MIN_ZOOM: f32 = 0.4;
+const MAX_ZOOM: f32 = 3.;
+const CLUE_LIST_WIDTH: f32 = 300.;
+/// Room taken by the clue pane and lists right of the grid, and by the toolbar above it
+const SIDE_PANEL_WIDTH: f32 = CLUE_LIST_WIDTH * 2. + 40.;
+const TOOLBAR_HEIGHT: f32 = 130.;
+const CLUE_LIST_HEIGHT: f32 = 400.;
+const LIBRARY_TITLE_WIDTH: f32 = 350.;
+const LIBRARY_AUTHOR_WIDTH: f32 = 200.;
+const LIBRARY_COLUMN_WIDTH: f32 = 110.;
+const LIBRARY_PROGRESS_WIDTH: f32 = 150.;
+
+type ClueStyler = fn(&Palette) -> container::Appearance;
+
+/// The iced frontend, a thin adapter around the headless `Board`
+pub struct App {
+    screen: Screen,
+    config: Config,
+    /// Name of the palette in use, one of `config.palettes`
+    palette_name: String,
+    window_size: Size,
+    /// Applied on top of the cell size that fits the window
+    zoom: f32,
+    /// Config problems, shown above every screen until dismissed
+    notices: Vec<String>,
+}
+
+enum Screen {
+    /// No puzzle given yet
+    Start,
+    LoadFailed { path: PathBuf, error: String },
+    Library(LibraryView),
+    /// Puzzles inside a zip archive, to pick one from
+    Archive { path: PathBuf, entries: Vec<ArchiveEntry> },
+    Solving(Box<Solving>),
+}
 
-const TEXT_SIZE: u16 = 30;
-const CELL_HEIGHT: f32 = 40.;
-const CELL_WIDTH: f32 = 40.;
+/// Puzzles found in the configured library directories
+#[derive(Default)]
+struct LibraryView {
+    entries: Vec<LibraryEntry>,
+    /// The directories are still being scanned
+    indexing: bool,
+    sort: LibrarySort,
+    filter: String,
+    hide_solved: bool,
+}
 
-type CellStyler = fn(&Theme) -> container::Appearance;
+/// A puzzle opened for solving
+struct Solving {
+    board: Board,
+    /// Updated with the fill on every Save
+    imported_puz: ImportedPuz,
+    /// The puzzle as it was loaded, for starting fresh
+    loaded_puz: ImportedPuz,
+    /// Where Save writes the puzzle, see `save_path_for`
+    save_path: PathBuf,
+    puzzle_hash: String,
+    /// The board was restored from an autosaved session
+    resumed_session: bool,
+    /// Geometry of the grid, cleared whenever the board changes
+    grid_cache: canvas::Cache,
+    /// Outcome of the last Save, shown next to the Save button
+    save_status: Option<String>,
+}
 
-impl Application for Board {
+impl Application for App {
     type Executor = executor::Default;
     type Message = Msg;
     type Theme = Theme;
-    type Flags = ();
+    /// Puzzle to open at startup
+    type Flags = Option<PathBuf>;
+
+    fn new(puz_path: Option<PathBuf>) -> (Self, Command<Msg>) {
+        let mut notices = Vec::new();
+        let config = Config::load().unwrap_or_else(|err| {
+            notices.push(format!("Could not load the config, using defaults: {err}"));
+            Config::default()
+        });
+        notices.extend(config.warnings.iter().map(|warning| format!("Config: {warning}")));
+
+        let screen = match puz_path {
+            Some(path) => open_screen(path),
+            None => Screen::Start,
+        };
+        let palette_name = config.palette.clone();
+        let app = App {
+            screen,
+            config,
+            palette_name,
+            window_size: window::Settings::default().size,
+            zoom: 1.,
+            notices,
+        };
+        (app, Command::none())
+    }
 
-    fn new(_flags: ()) -> (Self, Command<Msg>) {
-        // let content = vec![vec![Tile::Empty; 16]; 16];
-        let imported_puz = load_test_puz();
-        
-        let solution = solution_from(&imported_puz);
-        let content = solution.clone();
+    fn title(&self) -> String {
+        match &self.screen {
+            Screen::Solving(solving) => solving.board.title.clone(),
+            _ => "Cross-Iced".to_string()
+        }
+    }
 
-        let board = Board::new(
-            "Cross-Iced".to_string(),
-            content,
-            solution,
-            imported_puz.clues().clone(),
-            imported_puz.pos_2_clue_idx
-            );
-        
-        (board, Command::none())
+    fn view(&self) -> Element<'_, Msg> {
+        let screen = match &self.screen {
+            Screen::Start => message_view("Open a puzzle to start solving", "Open puzzle"),
+            Screen::Library(library) => library.view(&self.config),
+            Screen::Archive { path, entries } => archive_view(path, entries),
+            Screen::LoadFailed { path, error } => {
+                message_view(&format!("Could not open {path:?}: {error}"), "Open another puzzle")
+            },
+            Screen::Solving(solving) => {
+                let cell_size = fit_cell_size(
+                    self.window_size, solving.board.width(), solving.board.height(), self.zoom);
+                solving.view(self.palette(), self.palette_picker(), cell_size)
+            },
+        };
+
+        if self.notices.is_empty() {
+            return screen
+        }
+        let mut notices = Column::new().spacing(2);
+        for notice in &self.notices {
+            notices = notices.push(text(notice).size(16))
+        }
+        column![
+            row![notices, button(text("Dismiss")).on_press(Msg::DismissNotices)]
+                .spacing(10).padding(5).align_items(Alignment::Center),
+            screen,
+        ].into()
+    }
 
-    
+    fn theme(&self) -> Theme {
+        if self.palette().dark { Theme::Dark } else { Theme::Light }
     }
 
-    fn title(&self) -> String {
-        self.title.clone()
+    fn update(&mut self, message: Msg) -> Command<Msg> {
+        match message {
+            Msg::Open => Command::perform(pick_puzzle_file(), Msg::OpenPath),
+            Msg::OpenPath(Some(path)) => {
+                self.screen = open_screen(path);
+                Command::none()
+            },
+            Msg::OpenPath(None) => Command::none(),
+            Msg::OpenZipEntry(path, name) => {
+                self.screen = match Solving::open_zip_entry(&path, &name) {
+                    Ok(solving) => Screen::Solving(Box::new(solving)),
+                    Err(err) => Screen::LoadFailed { path: path.join(&name), error: err.to_string() },
+                };
+                Command::none()
+            },
+            Msg::SelectPalette(name) => {
+                self.palette_name = name;
+                self.redraw_grid();
+                Command::none()
+            },
+            Msg::DismissNotices => {
+                self.notices.clear();
+                Command::none()
+            },
+            Msg::WindowResized(size) => {
+                self.window_size = size;
+                self.redraw_grid();
+                Command::none()
+            },
+            Msg::ZoomIn | Msg::ZoomOut | Msg::ZoomReset => {
+                self.zoom = match message {
+                    Msg::ZoomIn => (self.zoom * ZOOM_STEP).min(MAX_ZOOM),
+                    Msg::ZoomOut => (self.zoom / ZOOM_STEP).max(MIN_ZOOM),
+                    _ => 1.
+                };
+                self.redraw_grid();
+                Command::none()
+            },
+            Msg::KeyReleased(key, modifiers) => match self.config.keymap.msg_for(&key, modifiers) {
+                Some(message) => self.update(message),
+                None => Command::none()
+            },
+            Msg::ShowLibrary => {
+                self.screen = Screen::Library(LibraryView { indexing: true, ..Default::default() });
+                Command::perform(index_library(self.config.library_dirs.clone()), Msg::LibraryIndexed)
+            },
+            message => match &mut self.screen {
+                Screen::Solving(solving) => solving.update(message),
+                Screen::Library(library) => {
+                    library.update(message);
+                    Command::none()
+                },
+                _ => Command::none()
+            }
+        }
+    }
+
+    fn subscription(&self) -> iced::Subscription<Self::Message> {
+        let keys = on_key_release::<Msg>(|k, m| Some(Msg::KeyReleased(k, m)));
+        let resizes = event::listen_with(|event, _status| match event {
+            Event::Window(_, window::Event::Resized { width, height }) => {
+                Some(Msg::WindowResized(Size::new(width as f32, height as f32)))
+            },
+            _ => None
+        });
+
+        Subscription::batch([keys, resizes])
+    }
+}
+
+
+impl App {
+    fn palette(&self) -> Palette {
+        self.config.palette_named(&self.palette_name)
     }
 
-    fn view(&self) -> Element<Msg> {
+    /// The grid geometry is cached, drop it when the palette or the cell size changes
+    fn redraw_grid(&self) {
+        if let Screen::Solving(solving) = &self.screen {
+            solving.grid_cache.clear()
+        }
+    }
+
+    fn palette_picker(&self) -> Element<'_, Msg> {
+        let names: Vec<String> = self.config.palettes.iter().map(|(name, _)| name.clone()).collect();
+        pick_list(names, Some(self.palette_name.clone()), Msg::SelectPalette).into()
+    }
+}
+
+impl LibraryView {
+    fn update(&mut self, message: Msg) {
+        match message {
+            Msg::LibraryIndexed(entries) => {
+                self.entries = entries;
+                self.indexing = false;
+                sort_entries(&mut self.entries, self.sort)
+            },
+            Msg::SortLibrary(sort) => {
+                self.sort = sort;
+                sort_entries(&mut self.entries, sort)
+            },
+            Msg::FilterLibrary(filter) => self.filter = filter,
+            Msg::ToggleHideSolved => self.hide_solved = !self.hide_solved,
+            _ => ()
+        }
+    }
+
+    fn view(&self, config: &Config) -> Element<'_, Msg> {
+        let controls = row![
+            text_input("Filter by title, author, date or size", &self.filter)
+                .on_input(Msg::FilterLibrary)
+                .width(400),
+            checkbox("Hide solved", self.hide_solved).on_toggle(|_| Msg::ToggleHideSolved),
+            button(text("Open file...")).on_press(Msg::Open),
+        ].spacing(10).align_items(Alignment::Center);
+
+        let sort_button = |label: &str, sort: LibrarySort, width: f32| {
+            let style = if self.sort == sort { theme::Button::Primary } else { theme::Button::Text };
+            button(text(label)).style(style).width(width).on_press(Msg::SortLibrary(sort))
+        };
+        let header = row![
+            sort_button("Title", LibrarySort::Title, LIBRARY_TITLE_WIDTH),
+            sort_button("Author", LibrarySort::Author, LIBRARY_AUTHOR_WIDTH),
+            sort_button("Date", LibrarySort::Date, LIBRARY_COLUMN_WIDTH),
+            sort_button("Size", LibrarySort::Size, LIBRARY_COLUMN_WIDTH),
+            sort_button("Progress", LibrarySort::Progress, LIBRARY_PROGRESS_WIDTH),
+        ].spacing(10);
+
+        let status = if self.indexing {
+            "Indexing puzzles...".to_string()
+        } else if config.library_dirs.is_empty() {
+            let config_path = Config::path().map(|path| format!("{path:?}")).unwrap_or_default();
+            format!("No library directories configured, add `library_dirs = [...]` to {config_path}")
+        } else {
+            format!("{} puzzles", self.entries.len())
+        };
+
+        let rows: Vec<Element<Msg>> = self.entries.iter()
+            .filter(|entry| entry.matches(&self.filter) && !(self.hide_solved && entry.solved))
+            .map(|entry| {
+                let progress_label = if entry.solved {
+                    "Solved".to_string()
+                } else {
+                    format!("{:.0}%", entry.progress * 100.)
+                };
+                let entry_row = row![
+                    text(&entry.title).width(LIBRARY_TITLE_WIDTH),
+                    text(&entry.author).width(LIBRARY_AUTHOR_WIDTH),
+                    text(&entry.date).width(LIBRARY_COLUMN_WIDTH),
+                    text(entry.size()).width(LIBRARY_COLUMN_WIDTH),
+                    progress_bar(0.0..=1.0, entry.progress).width(LIBRARY_PROGRESS_WIDTH).height(12),
+                    text(progress_label),
+                ].spacing(10).align_items(Alignment::Center);
+
+                mouse_area(container(entry_row).padding(5))
+                    .on_press(match &entry.zip_entry {
+                        Some(name) => Msg::OpenZipEntry(entry.path.clone(), name.clone()),
+                        None => Msg::OpenPath(Some(entry.path.clone())),
+                    })
+                    .into()
+            })
+            .collect();
+
+        column![
+            text("Library").size(30),
+            controls,
+            text(status).size(16),
+            header,
+            scrollable(Column::from_vec(rows)).height(Length::Fill),
+        ].spacing(10).padding(10).into()
+    }
+}
+
+impl Solving {
+    fn open(puz_path: PathBuf) -> io::Result<Self> {
+        let imported_puz = loader::load_puzzle(&puz_path)?;
+        Ok(Self::new(imported_puz, save_path_for(&puz_path)))
+    }
+
+    /// Saving writes a .puz named after the archive and the entry next to the archive,
+    /// which is left untouched
+    fn open_zip_entry(zip_path: &Path, entry_name: &str) -> io::Result<Self> {
+        let imported_puz = loader::load_from_zip(zip_path, entry_name)?;
+        let mut file_name = zip_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".");
+        file_name.push(Path::new(entry_name).file_name().unwrap_or(entry_name.as_ref()));
+        Ok(Self::new(imported_puz, save_path_for(&zip_path.with_file_name(file_name))))
+    }
+
+    fn new(imported_puz: ImportedPuz, save_path: PathBuf) -> Self {
+        let mut board = Board::from_puzzle("Cross-Iced".to_string(), &imported_puz);
+        let puzzle_hash = puzzle_hash(&imported_puz);
+
+        let resumed_session = match Session::load(&puzzle_hash) {
+            Ok(Some(session)) => {
+                session.apply_to(&mut board);
+                true
+            },
+            Ok(None) => false,
+            Err(err) => {
+                eprintln!("Could not load the saved session: {err}");
+                false
+            }
+        };
+
+        Solving {
+            board,
+            loaded_puz: imported_puz.clone(),
+            imported_puz,
+            save_path,
+            puzzle_hash,
+            resumed_session,
+            grid_cache: canvas::Cache::new(),
+            save_status: None,
+        }
+    }
+
+    fn view<'a>(&'a self, palette: Palette, palette_picker: Element<'a, Msg>, cell_size: f32) -> Element<'a, Msg> {
         row![
             text(" "),
             column![
             vertical_space(),
-            row![self.draw_board(), self.draw_clue_pane()].spacing(10),
+            self.draw_toolbar(palette_picker),
+            row![
+                GridCanvas { board: &self.board, palette, cell_size, cache: &self.grid_cache }.view(),
+                column![
+                    self.draw_clue_pane(palette),
+                    self.draw_clue_lists(palette),
+                    self.draw_completion(palette)
+                ].spacing(20)
+            ].spacing(10),
             vertical_space()
-            ]
+            ].spacing(10)
         ].into()
     }
-    
+
     fn update(&mut self, message: Msg) -> Command<Msg> {
+        let prev_cursor = (self.board.cur_pos, self.board.cur_dir);
+        let prev_grid = self.grid_state();
+        let prev_session = Session::from_board(&self.puzzle_hash, &self.board);
+        // the session was just removed, it gets saved again with the next change
+        let start_fresh = matches!(message, Msg::StartFresh);
+
         match message {
-            Msg::MoveCursor(dir) => self.move_cursor_until_not_black(dir),
-            Msg::TypeLetter(ch) => self.type_letter(ch, true),
-            Msg::ClearCell => self.type_letter(' ', false),
-            Msg::ToggleTypingDir => self.toggle_typing_dir() 
+            Msg::MoveCursor(dir) => self.board.move_cursor_until_not_black(dir),
+            Msg::TypeLetter(ch) => self.board.type_letter(ch, true),
+            Msg::ClearCell => self.board.clear_cell(),
+            Msg::Backspace => self.board.backspace(),
+            Msg::Advance => self.board.advance(),
+            Msg::ClearWord => self.board.clear_word(),
+            Msg::ToggleTypingDir => self.board.toggle_typing_dir(),
+            Msg::Undo => { self.board.undo(); },
+            Msg::Redo => { self.board.redo(); },
+            Msg::ToggleAutocheck => self.board.toggle_autocheck(),
+            Msg::CheckSquare => { self.board.check_square(); },
+            Msg::CheckWord => { self.board.check_word(); },
+            Msg::CheckPuzzle => { self.board.check_puzzle(); },
+            Msg::RevealSquare => self.board.reveal_square(),
+            Msg::RevealWord => self.board.reveal_word(),
+            Msg::RevealPuzzle => self.board.reveal_puzzle(),
+            Msg::TogglePencil => self.board.toggle_pencil(),
+            Msg::InkWord => self.board.ink_word(),
+            Msg::NextClue => self.board.next_clue(),
+            Msg::PrevClue => self.board.prev_clue(),
+            Msg::ClickCell(pos) => self.board.click_cell(pos),
+            Msg::SelectEntry(start, dir) => self.board.select(start, dir),
+            Msg::ToggleSkipFullEntries => {
+                self.board.options.skip_full_entries = !self.board.options.skip_full_entries
+            },
+            Msg::ToggleSkipFilledCells => {
+                self.board.options.skip_filled_cells = !self.board.options.skip_filled_cells
+            },
+            Msg::ToggleJumpToNextEntry => {
+                self.board.options.jump_to_next_entry = !self.board.options.jump_to_next_entry
+            },
+            Msg::StartFresh => {
+                if let Err(err) = Session::remove(&self.puzzle_hash) {
+                    eprintln!("Could not remove the saved session: {err}")
+                }
+                self.board = Board::from_puzzle(self.board.title.clone(), &self.loaded_puz);
+                self.resumed_session = false;
+            },
+            Msg::Save => {
+                self.save_status = Some(match self.save() {
+                    Ok(()) => format!("Saved to {}", self.save_path.display()),
+                    Err(err) => format!("Could not save {}: {err}", self.save_path.display()),
+                });
+            },
+            // handled by App or the library
+            Msg::Open | Msg::OpenPath(_) | Msg::OpenZipEntry(..) | Msg::ShowLibrary | Msg::LibraryIndexed(_)
+                | Msg::SortLibrary(_) | Msg::FilterLibrary(_) | Msg::ToggleHideSolved
+                | Msg::KeyReleased(..) | Msg::SelectPalette(_) | Msg::DismissNotices | Msg::WindowResized(_)
+                | Msg::ZoomIn | Msg::ZoomOut | Msg::ZoomReset => (),
+        }
+
+        if self.grid_state() != prev_grid {
+            self.grid_cache.clear();
+        }
+
+        let session = Session::from_board(&self.puzzle_hash, &self.board);
+        if !start_fresh && !session.same_progress(&prev_session) {
+            if let Err(err) = session.save() {
+                eprintln!("Could not autosave the session: {err}")
+            }
         }
 
-        Command::none()
+        if (self.board.cur_pos, self.board.cur_dir) != prev_cursor {
+            self.scroll_clue_lists()
+        } else {
+            Command::none()
+        }
     }
 
-    fn subscription(&self) -> iced::Subscription<Self::Message> {
-        on_key_release::<Msg>(|k, m| {
-            println!("On key release: k={k:?} mod={m:?}");
-
-            match k {
-                Key::Named(Named::ArrowLeft) => Some(Msg::MoveCursor(Direction::Left)),
-                Key::Named(Named::ArrowRight) => {
-                    if m.contains(Modifiers::SHIFT) || m.contains(Modifiers::CTRL) {    
-                        Some(Msg::ToggleTypingDir)
-                    } else {
-                        Some(Msg::MoveCursor(Direction::Right))
-                    }
-                },
-                Key::Named(Named::ArrowUp) => Some(Msg::MoveCursor(Direction::Up)),
-                Key::Named(Named::ArrowDown) => {
-                    if m.contains(Modifiers::SHIFT) || m.contains(Modifiers::CTRL) {
-                        Some(Msg::ToggleTypingDir)
-                    } else {
-                        Some(Msg::MoveCursor(Direction::Down))    
-                    }
-                },
-                Key::Named(Named::Backspace) => Some(Msg::ClearCell),
-                Key::Named(Named::Space) => Some(Msg::ClearCell),
-                Key::Character(st) => {
-                    let c_str = st.to_string();
-                    if c_str.len() > 0 {
-                        Some(Msg::TypeLetter(c_str.chars().last().unwrap().to_ascii_uppercase()))
-                    } else {
-                        None
-                    }
-                }
-                _ => None
+    /// What the grid drawing depends on besides the palette and the cell size. The cursor
+    /// and direction also determine the selected and referenced entries.
+    fn grid_state(&self) -> (Vec<Vec<Cell>>, Vec<Vec<CellMarks>>, Pos, TypingDir, bool) {
+        let board = &self.board;
+        (board.content.clone(), board.marks.clone(), board.cur_pos, board.cur_dir, board.autocheck)
+    }
+
+    fn draw_toolbar<'a>(&'a self, palette_picker: Element<'a, Msg>) -> Element<'a, Msg> {
+        let options = &self.board.options;
+        // disabled for puzzles published without their answers
+        let has_solution = self.board.has_solution();
+        let solution_button = |label, msg: Msg| button(text(label)).on_press_maybe(has_solution.then_some(msg));
+        let commands = row![
+            solution_button("Check square", Msg::CheckSquare),
+            solution_button("Check word", Msg::CheckWord),
+            solution_button("Check puzzle", Msg::CheckPuzzle),
+            solution_button("Reveal square", Msg::RevealSquare),
+            solution_button("Reveal word", Msg::RevealWord),
+            solution_button("Reveal puzzle", Msg::RevealPuzzle),
+            button(text("Ink word")).on_press(Msg::InkWord),
+            button(text("Save")).on_press(Msg::Save),
+            button(text("Open...")).on_press(Msg::Open),
+            button(text("Library")).on_press(Msg::ShowLibrary),
+            text(self.save_status.as_deref().unwrap_or_default()).size(16),
+        ].spacing(10).align_items(Alignment::Center);
+
+        let toggles = row![
+            checkbox("Autocheck", self.board.autocheck)
+                .on_toggle_maybe(has_solution.then_some(|_| Msg::ToggleAutocheck)),
+            checkbox("Pencil", self.board.pencil).on_toggle(|_| Msg::TogglePencil),
+            checkbox("Skip filled squares", options.skip_filled_cells)
+                .on_toggle(|_| Msg::ToggleSkipFilledCells),
+            checkbox("Jump to next clue", options.jump_to_next_entry)
+                .on_toggle(|_| Msg::ToggleJumpToNextEntry),
+            checkbox("Skip full entries", options.skip_full_entries)
+                .on_toggle(|_| Msg::ToggleSkipFullEntries),
+            text("Colours:"),
+            palette_picker,
+        ].spacing(10).align_items(Alignment::Center);
+
+        let mut toolbar = column![commands, toggles].spacing(5);
+        if self.resumed_session {
+            toolbar = toolbar.push(row![
+                text("Resumed your saved progress").size(16),
+                button(text("Start fresh")).on_press(Msg::StartFresh),
+            ].spacing(10).align_items(Alignment::Center));
+        }
+
+        toolbar.into()
+    }
+
+    /// Writes to a temporary file first, so that a failed write leaves the original intact
+    fn save(&mut self) -> io::Result<()> {
+        self.board.store_in_puzzle(&mut self.imported_puz);
+        let save_path = &self.save_path;
+        let tmp_path = save_path.with_extension("puz.tmp");
+
+        let written = File::create(&tmp_path).and_then(|file| {
+            let mut f = BufWriter::new(file);
+            write_puzzle(&self.imported_puz, &mut f)?;
+            f.into_inner().map_err(|err| err.into_error())?.sync_all()
+        });
+        match written {
+            Ok(()) => fs::rename(&tmp_path, save_path),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp_path);
+                Err(err)
             }
-        })
+        }
     }
-}
 
+    fn draw_clue_pane(&self, palette: Palette) -> Element<'_, Msg> {
+        let clue_line = |clue: Option<Clue>| {
+            clue.map(|clue| format!("{} {}", clue.header(), clue.text)).unwrap_or_default()
+        };
+
+        let mut lines: Vec<Element<Msg>> = vec![
+            text(clue_line(self.board.current_clue())).size(20).into(),
+            text(clue_line(self.board.crossing_clue())).size(16).style(palette.crossing_text).into(),
+        ];
+        for clue in self.board.referenced_clues() {
+            let line = container(text(format!("See {}", clue_line(Some(clue)))).size(16))
+                .padding(3)
+                .style(move |_th: &Theme| container::Appearance {
+                    background: Some(iced::Background::Color(palette.reference)),
+                    text_color: Some(palette.text),
+                    ..Default::default()
+                });
+            lines.push(line.into())
+        }
+
+        Column::from_vec(lines).width(CLUE_LIST_WIDTH * 2.).spacing(5).into()
+    }
 
-impl Board {
-    fn draw_clue_pane(&self) -> Element<Msg> {
-        text(self.current_clue()).size(20).into()
+    fn draw_clue_lists(&self, palette: Palette) -> Element<'_, Msg> {
+        row![
+            self.draw_clue_list(TypingDir::Across, palette),
+            self.draw_clue_list(TypingDir::Down, palette),
+        ].spacing(10).into()
     }
-    
-    fn draw_board(&self) -> Element<Msg> {
-        let mut col_children: Vec<Element<Msg>> = Vec::new();
-        for (r_idx, content_row) in self.content.iter().enumerate() {
-            let mut row: Vec<Element<Msg>> = Vec::new();
-            for (c_idx, tile) in content_row.iter().enumerate() {
-                let pos = Pos::new(r_idx, c_idx);
-                let styler : CellStyler = if pos == self.cur_pos {
-                        active_cell
-                    } else if self.cur_sel.contains(&pos) {
-                        selected_cell
+
+    fn draw_clue_list(&self, dir: TypingDir, palette: Palette) -> Element<'_, Msg> {
+        let board = &self.board;
+        let crossing = board.crossing_entry();
+
+        let items: Vec<Element<Msg>> = board.clue_list(dir).into_iter()
+            .map(|clue| {
+                let entry = (clue.start, dir);
+                let styler: ClueStyler = if board.is_active_entry(&entry) {
+                        active_clue
+                    } else if crossing == Some(entry) {
+                        crossing_clue
                     } else {
-                        inactive_cell
+                        inactive_clue
                     };
 
-                let (text_elem, styler) : (Element<_, _>, CellStyler) =
-                    match tile {
-                        Cell::Empty => (cell(' ', true), styler),
-                        Cell::Black => (cell(' ', true), black_block),
-                        Cell::OccupiedRight(c) => (cell(*c, true), styler),
-                        Cell::OccupiedWrong(c) => (cell(*c, false), styler)
-                    }
-                ;
-
-                let cont = container(text_elem)
-                    .height(CELL_HEIGHT)
-                    .width(CELL_WIDTH)
-                    .style(styler);
-                row.push(cont.into())
+                let item = container(text(format!("{}. {}", clue.number, clue.text)).size(16))
+                    .width(Length::Fill)
+                    .padding(3)
+                    .style(move |_th: &Theme| styler(&palette));
+                mouse_area(item).on_press(Msg::SelectEntry(clue.start, dir)).into()
+            })
+            .collect();
+
+        let heading = match dir {
+            TypingDir::Across => "Across",
+            TypingDir::Down => "Down",
+        };
+
+        column![
+            text(heading).size(20),
+            scrollable(Column::from_vec(items).spacing(2))
+                .id(clue_list_id(dir))
+                .height(CLUE_LIST_HEIGHT),
+        ].width(CLUE_LIST_WIDTH).spacing(5).into()
+    }
+
+    /// Scrolls both clue lists so that the active and crossing clues are in view
+    fn scroll_clue_lists(&self) -> Command<Msg> {
+        let board = &self.board;
+        let crossing = board.crossing_entry();
+
+        let commands = [TypingDir::Across, TypingDir::Down].map(|dir| {
+            let clues = board.clue_list(dir);
+            let focus_idx = clues.iter().position(|clue| {
+                let entry = (clue.start, dir);
+                board.is_active_entry(&entry) || crossing == Some(entry)
+            });
+
+            match focus_idx {
+                Some(idx) if clues.len() > 1 => {
+                    let y = idx as f32 / (clues.len() - 1) as f32;
+                    scrollable::snap_to(clue_list_id(dir), RelativeOffset{x: 0., y})
+                },
+                _ => Command::none()
             }
-            let row = Row::from_vec(row).spacing(0);
-            col_children.push(row.into())
-        }
+        });
 
-        Column::from_vec(col_children).spacing(0).into()
+        Command::batch(commands)
+    }
+
+    fn draw_completion(&self, palette: Palette) -> Element<'_, Msg> {
+        let board = &self.board;
+        match board.completion {
+            Completion::InProgress => column![].into(),
+            Completion::Pencilled => text("Ink your pencilled letters to finish").size(20).into(),
+            Completion::Incorrect(num_wrong) => {
+                let msg = if num_wrong == 1 {
+                    "1 square is wrong".to_string()
+                } else {
+                    format!("{num_wrong} squares are wrong")
+                };
+                text(msg).size(20).style(palette.wrong).into()
+            },
+            Completion::Solved => {
+                let stats = board.solve_stats();
+                let verdict = if board.reveals_used == 0 && board.checks_used == 0 {
+                    "Clean solve!".to_string()
+                } else {
+                    format!("{clean} of {open} squares solved without help", 
+                        clean=stats.clean, open=stats.open_cells)
+                };
+
+                column![
+                    text("Congratulations, puzzle solved!").size(28).style(palette.solved),
+                    text(format!("Time: {}", format_duration(board.timer.elapsed()))).size(20),
+                    text(format!("Checks used: {}", board.checks_used)).size(20),
+                    text(format!("Reveals used: {}", board.reveals_used)).size(20),
+                    text(verdict).size(20),
+                ].spacing(5).into()
+            }
+        }
     }
 }
 
-fn load_test_puz() -> ImportedPuz {
-    let path = "/home/teo/Downloads/wsj240702.puz";
-    let mut f = BufReader::new(File::open(path).unwrap());
+/// Side of a cell such that the whole grid fits next to the clues, times `zoom`
+fn fit_cell_size(window_size: Size, width: usize, height: usize, zoom: f32) -> f32 {
+    if width == 0 || height == 0 {
+        return MIN_CELL_SIZE
+    }
 
-    // let header = read_header(&mut f).unwrap();
-    import_puz::import_puzzle(&mut f).unwrap()
+    let avail_width = (window_size.width - SIDE_PANEL_WIDTH).max(0.);
+    let avail_height = (window_size.height - TOOLBAR_HEIGHT).max(0.);
+    let fitting = (avail_width / width as f32).min(avail_height / height as f32);
 
+    (fitting.clamp(MIN_CELL_SIZE, MAX_CELL_SIZE) * zoom).floor()
 }
-    
-fn solution_from(imported_puz: &ImportedPuz) -> Vec<Vec<Cell>> {
-    let mut ret = Vec::new();
 
-    for r_idx in 0..imported_puz.height() {
-        let row_ret = (0..imported_puz.width()).map (
-            |c_idx| {
-                match imported_puz.solution_at(r_idx, c_idx) {
-                    '.' => Cell::Black,
-                    '_' => Cell::Empty,
-                    c => Cell::OccupiedRight(c)
-                }
-        });
-        ret.push(row_ret.collect())
+/// .puz files are saved in place. Other formats get `.puz` appended to their whole file
+/// name, so that `x.ipuz` is saved as `x.ipuz.puz` and never overwrites a sibling `x.puz`.
+fn save_path_for(path: &Path) -> PathBuf {
+    if loader::PuzFormat::from_path(path) == Some(loader::PuzFormat::Puz) {
+        return path.to_path_buf()
     }
+    let mut save_path = path.as_os_str().to_os_string();
+    save_path.push(".puz");
+    PathBuf::from(save_path)
+}
 
-    ret
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
 }
 
-fn active_cell(_th: &Theme) -> container::Appearance {
-    container::Appearance {
-        background: Some(iced::Background::Color(Color::from_rgb8(  150, 250, 255))),
-        ..default_cell_appearance(_th)
+fn open_screen(path: PathBuf) -> Screen {
+    if loader::is_zip(&path) {
+        return match loader::list_zip(&path) {
+            Ok(entries) => Screen::Archive { path, entries },
+            Err(err) => Screen::LoadFailed { path, error: err.to_string() },
+        }
+    }
+
+    match Solving::open(path.clone()) {
+        Ok(solving) => Screen::Solving(Box::new(solving)),
+        Err(err) => Screen::LoadFailed { path, error: err.to_string() },
     }
 }
 
-fn selected_cell(_th: &Theme) -> container::Appearance {
-    container::Appearance {
-        background: Some(iced::Background::Color(Color::from_rgb8(  200, 250, 255))),
-        /* border: Border{
-            color: Color::from_rgb8(  150, 250, 255),
-            radius: 0.into(),
-            width: 1.0,
+async fn index_library(dirs: Vec<PathBuf>) -> Vec<LibraryEntry> {
+    scan_library(&dirs)
+}
 
-        },*/
-        ..default_cell_appearance(_th)
-    }
+async fn pick_puzzle_file() -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .set_title("Open puzzle")
+        .add_filter("Puzzles", &["puz", "ipuz", "json", "zip"])
+        .pick_file()
+        .await
+        .map(|handle| handle.path().to_path_buf())
 }
 
-fn inactive_cell(_th: &Theme) -> container::Appearance {
-    container::Appearance {
-        ..default_cell_appearance(_th)
+/// Start and error screens: a message with a button to open a puzzle
+fn message_view<'a>(message: &str, open_label: &str) -> Element<'a, Msg> {
+    container(
+        column![
+            text("Cross-Iced").size(40),
+            text(message).size(20),
+            row![
+                button(text(open_label)).on_press(Msg::Open),
+                button(text("Library")).on_press(Msg::ShowLibrary),
+            ].spacing(10),
+        ].spacing(20).align_items(Alignment::Center)
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x()
+    .center_y()
+    .into()
+}
+
+/// Entries of a zip archive, each opening its puzzle when clicked
+fn archive_view<'a>(path: &Path, entries: &[ArchiveEntry]) -> Element<'a, Msg> {
+    let rows: Vec<Element<Msg>> = entries.iter()
+        .map(|entry| {
+            let entry_row = row![
+                text(&entry.title).width(LIBRARY_TITLE_WIDTH),
+                text(&entry.name).width(LIBRARY_AUTHOR_WIDTH),
+                text(format!("{}x{}", entry.width, entry.height)).width(LIBRARY_COLUMN_WIDTH),
+            ].spacing(10);
+            mouse_area(container(entry_row).padding(5))
+                .on_press(Msg::OpenZipEntry(path.to_path_buf(), entry.name.clone()))
+                .into()
+        })
+        .collect();
+
+    let status = if entries.is_empty() {
+        "No puzzles found in this archive".to_string()
+    } else {
+        format!("{} puzzles", entries.len())
+    };
+
+    column![
+        text(format!("{}", path.display())).size(30),
+        row![
+            text(status).size(16),
+            button(text("Open another file...")).on_press(Msg::Open),
+            button(text("Library")).on_press(Msg::ShowLibrary),
+        ].spacing(10).align_items(Alignment::Center),
+        scrollable(Column::from_vec(rows)).height(Length::Fill),
+    ].spacing(10).padding(10).into()
+}
+
+fn clue_list_id(dir: TypingDir) -> scrollable::Id {
+    match dir {
+        TypingDir::Across => scrollable::Id::new("across_clues"),
+        TypingDir::Down => scrollable::Id::new("down_clues"),
     }
 }
 
-fn default_cell_appearance(_th: &Theme) -> container::Appearance {
+fn active_clue(palette: &Palette) -> container::Appearance {
     container::Appearance {
-        background: Some(iced::Background::Color(Color::from_rgb8(  255, 255, 255))),
-        border: Border{
-            color: Color::BLACK,
-            radius: 0.into(),
-            width: 1.0,
-
-        },
+        background: Some(iced::Background::Color(palette.cursor)),
+        text_color: Some(palette.text),
         ..Default::default()
     }
 }
 
-fn black_block(_th: &Theme) -> container::Appearance {
+fn crossing_clue(palette: &Palette) -> container::Appearance {
     container::Appearance {
-        background: Some(iced::Background::Color(Color::from_rgb8(10, 10, 10))),
+        background: Some(iced::Background::Color(palette.crossing)),
+        text_color: Some(palette.text),
         ..Default::default()
     }
 }
 
-
-fn cell<'a>(ch: char, right: bool) -> Element<'a, Msg, Theme> {
-    let content = ch.to_string();
-    text(content)
-        .size(TEXT_SIZE)
-        .height(CELL_HEIGHT)
-        .width(CELL_WIDTH)
-        .style(if right {Color::BLACK} else {Color::from_rgb8(255, 0, 0)})
-        .horizontal_alignment(Horizontal::Center)
-        .into()
+fn inactive_clue(_palette: &Palette) -> container::Appearance {
+    container::Appearance::default()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_paths_never_replace_another_puz() {
+        assert_eq!(save_path_for(Path::new("dir/x.puz")), PathBuf::from("dir/x.puz"));
+        assert_eq!(save_path_for(Path::new("dir/x.ipuz")), PathBuf::from("dir/x.ipuz.puz"));
+        assert_eq!(save_path_for(Path::new("dir/x.json")), PathBuf::from("dir/x.json.puz"));
+    }
+}
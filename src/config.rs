@@ -0,0 +1,134 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use toml::{Table, Value};
+
+use crate::util::invalid_data;
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+
+/// User settings, read from `config.toml` in the user config directory
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directories scanned for the puzzle library
+    pub library_dirs: Vec<PathBuf>,
+    pub keymap: Keymap,
+    /// The shipped palettes followed by the user's own
+    pub palettes: Vec<(String, Palette)>,
+    /// Name of the palette used at startup
+    pub palette: String,
+    /// Problems worth reporting that didn't prevent loading, e.g. conflicting key bindings
+    pub warnings: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            library_dirs: Vec::new(),
+            keymap: Keymap::default(),
+            palettes: Palette::presets(),
+            palette: "light".to_string(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The palette called `name`, the first one if there is none
+    pub fn palette_named(&self, name: &str) -> Palette {
+        self.palettes.iter()
+            .find(|(palette_name, _)| palette_name == name)
+            .or(self.palettes.first())
+            .map(|(_, palette)| *palette)
+            .unwrap_or(Palette::LIGHT)
+    }
+
+    pub fn path() -> io::Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user config directory"))?;
+        Ok(config_dir.join("cross-iced").join("config.toml"))
+    }
+
+    /// The user's config, defaults when there is no config file
+    pub fn load() -> io::Result<Self> {
+        match fs::read_to_string(Self::path()?) {
+            Ok(text) => Self::from_toml(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        let root: Table = text.parse().map_err(invalid_data)?;
+
+        let library_dirs = match root.get("library_dirs") {
+            Some(Value::Array(dirs)) => dirs.iter()
+                .map(|dir| dir.as_str().map(expand_home)
+                    .ok_or_else(|| invalid_data("config: library_dirs must hold strings")))
+                .collect::<io::Result<_>>()?,
+            Some(_) => return Err(invalid_data("config: library_dirs must be an array")),
+            None => Vec::new()
+        };
+
+        let (keymap, mut warnings) = match root.get("keymap") {
+            Some(Value::Table(keymap)) => Keymap::from_toml(keymap)?,
+            Some(_) => return Err(invalid_data("config: keymap must be a table")),
+            None => (Keymap::default(), Vec::new())
+        };
+
+        let mut palettes = Palette::presets();
+        match root.get("palettes") {
+            Some(Value::Table(user_palettes)) => {
+                for (name, palette) in user_palettes {
+                    let (palette, palette_warnings) = palette.as_table()
+                        .ok_or_else(|| invalid_data(format!("config: palette {name} must be a table")))
+                        .and_then(Palette::from_toml)?;
+                    warnings.extend(palette_warnings.into_iter().map(|warning| format!("palette {name}: {warning}")));
+                    // user palettes may replace the shipped ones
+                    match palettes.iter_mut().find(|(existing, _)| existing == name) {
+                        Some(existing) => existing.1 = palette,
+                        None => palettes.push((name.clone(), palette))
+                    }
+                }
+            },
+            Some(_) => return Err(invalid_data("config: palettes must be a table")),
+            None => ()
+        }
+
+        let palette = match root.get("palette") {
+            Some(Value::String(name)) if palettes.iter().any(|(existing, _)| existing == name) => name.clone(),
+            Some(Value::String(name)) => return Err(invalid_data(format!("config: unknown palette {name:?}"))),
+            Some(_) => return Err(invalid_data("config: palette must be a string")),
+            None => "light".to_string()
+        };
+
+        Ok(Config { library_dirs, keymap, palettes, palette, warnings })
+    }
+}
+
+/// `~/puzzles` -> `/home/<user>/puzzles`
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_palette_colours_are_warnings() {
+        let config = Config::from_toml(r##"
+            palette = "mine"
+            [palettes.mine]
+            cursor = "orange"
+        "##).unwrap();
+
+        assert_eq!(config.palette_named("mine").cursor, Palette::LIGHT.cursor);
+        assert_eq!(config.warnings.len(), 1);
+        assert!(config.warnings[0].starts_with("palette mine: cursor"));
+    }
+}
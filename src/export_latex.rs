@@ -0,0 +1,195 @@
+use std::fmt::Write;
+
+use crate::api_types::{Pos, TypingDir};
+use crate::import_puz::ImportedPuz;
+
+/// Options for typesetting a puzzle with the `cwpuzzle` LaTeX package
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatexOptions {
+    /// Print the solution letters in the grid instead of a blank grid
+    pub solved: bool,
+    /// Emit the `PuzzleClues` environments after the grid
+    pub with_clues: bool,
+}
+
+/// Renders `puz` as a `Puzzle` environment followed by the Across and Down `PuzzleClues`.
+/// The output is meant to be `\input` into a document that loads `cwpuzzle`.
+pub fn export_latex(puz: &ImportedPuz, opts: &LatexOptions) -> String {
+    let mut out = String::new();
+
+    if opts.solved {
+        out.push_str("\\PuzzleSolution\n");
+    } else {
+        out.push_str("\\PuzzleSolution[false]\n");
+    }
+
+    write_grid(&mut out, puz);
+
+    if opts.with_clues {
+        write_clues(&mut out, puz, TypingDir::Across, "Across");
+        write_clues(&mut out, puz, TypingDir::Down, "Down");
+    }
+
+    out
+}
+
+fn write_grid(out: &mut String, puz: &ImportedPuz) {
+    let cell_numbers = puz.cell_numbers();
+
+    writeln!(out, "\\begin{{Puzzle}}{{{w}}}{{{h}}}", w=puz.width(), h=puz.height()).unwrap();
+    for r_idx in 0..puz.height() {
+        for c_idx in 0..puz.width() {
+            let ch = puz.solution_at(r_idx, c_idx);
+            if ch == '.' {
+                out.push_str("|*    ");
+                continue
+            }
+
+            let number = cell_numbers.number_at(&Pos::new(r_idx, c_idx))
+                .map(|num| num.to_string())
+                .unwrap_or_default();
+            let style = if puz.is_circled(r_idx, c_idx) { "O" } else { "" };
+
+            if !style.is_empty() {
+                write!(out, "|[{number}][{style}]{ch} ").unwrap();
+            } else if !number.is_empty() {
+                write!(out, "|[{number}]{ch} ").unwrap();
+            } else {
+                write!(out, "|{ch}    ").unwrap();
+            }
+        }
+        out.push_str("|.\n");
+    }
+    out.push_str("\\end{Puzzle}\n");
+}
+
+fn write_clues(out: &mut String, puz: &ImportedPuz, dir: TypingDir, heading: &str) {
+    let cell_numbers = puz.cell_numbers();
+
+    writeln!(out, "\n\\begin{{PuzzleClues}}{{\\textbf{{{heading}}}}}").unwrap();
+    for ((pos, clue_dir), clue_idx) in puz.pos_2_clue_idx.0.iter() {
+        if *clue_dir != dir {
+            continue
+        }
+        let number = cell_numbers.number_at(pos).unwrap_or_default();
+        let clue = puz.clues().get(*clue_idx).map(|s| s.as_str()).unwrap_or("");
+
+        writeln!(out, "\\Clue{{{number}}}{{{answer}}}{{{clue}}}",
+            answer=escape_latex(&answer_at(puz, pos, dir)),
+            clue=escape_latex(clue)).unwrap();
+    }
+    out.push_str("\\end{PuzzleClues}\n");
+}
+
+fn answer_at(puz: &ImportedPuz, start: &Pos, dir: TypingDir) -> String {
+    let mut answer = String::new();
+    let mut pos = *start;
+
+    while pos.row < puz.height() && pos.col < puz.width() {
+        let ch = puz.solution_at(pos.row, pos.col);
+        if ch == '.' {
+            break
+        }
+        answer.push(ch);
+        match dir {
+            TypingDir::Across => pos.col += 1,
+            TypingDir::Down => pos.row += 1,
+        }
+    }
+
+    answer
+}
+
+/// Escapes the characters that have a special meaning in LaTeX text mode
+pub fn escape_latex(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => ret.push_str("\\textbackslash{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                ret.push('\\');
+                ret.push(ch)
+            },
+            '~' => ret.push_str("\\textasciitilde{}"),
+            '^' => ret.push_str("\\textasciicircum{}"),
+            '|' => ret.push_str("\\textbar{}"),
+            '<' => ret.push_str("\\textless{}"),
+            '>' => ret.push_str("\\textgreater{}"),
+            _ => ret.push(ch)
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::import_puz::{PuzStrings, GEXT_CIRCLED};
+
+    /// C A T
+    /// A # E
+    /// B E D
+    fn small_puzzle() -> ImportedPuz {
+        let solution = vec![
+            vec!['C', 'A', 'T'],
+            vec!['A', '.', 'E'],
+            vec!['B', 'E', 'D'],
+        ];
+        let player_state = vec![
+            vec!['-', '-', '-'],
+            vec!['-', '.', '-'],
+            vec!['-', '-', '-'],
+        ];
+        let clues = BTreeMap::from([
+            ((1, TypingDir::Across), "Pet & friend".to_string()),
+            ((1, TypingDir::Down), "Taxi".to_string()),
+            ((2, TypingDir::Down), "Talk".to_string()),
+            ((3, TypingDir::Across), "Bunk".to_string()),
+        ]);
+        let mut puz = ImportedPuz::from_grid(solution, player_state, PuzStrings::default(), &clues, Vec::new());
+        puz.set_markup_at(2, 2, GEXT_CIRCLED);
+        puz
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape_latex("& % $ # _ { }"), "\\& \\% \\$ \\# \\_ \\{ \\}");
+        assert_eq!(escape_latex("~^\\"), "\\textasciitilde{}\\textasciicircum{}\\textbackslash{}");
+        assert_eq!(escape_latex("plain text"), "plain text");
+    }
+
+    #[test]
+    fn blank_grid_and_clues() {
+        let opts = LatexOptions { solved: false, with_clues: true };
+        assert_eq!(export_latex(&small_puzzle(), &opts), "\
+\\PuzzleSolution[false]
+\\begin{Puzzle}{3}{3}
+|[1]C |A    |[2]T |.
+|A    |*    |E    |.
+|[3]B |E    |[][O]D |.
+\\end{Puzzle}
+
+\\begin{PuzzleClues}{\\textbf{Across}}
+\\Clue{1}{CAT}{Pet \\& friend}
+\\Clue{3}{BED}{Bunk}
+\\end{PuzzleClues}
+
+\\begin{PuzzleClues}{\\textbf{Down}}
+\\Clue{1}{CAB}{Taxi}
+\\Clue{2}{TED}{Talk}
+\\end{PuzzleClues}
+");
+    }
+
+    #[test]
+    fn solved_grid_without_clues() {
+        let opts = LatexOptions { solved: true, with_clues: false };
+        let latex = export_latex(&small_puzzle(), &opts);
+        assert!(latex.starts_with("\\PuzzleSolution\n\\begin{Puzzle}{3}{3}\n"));
+        assert!(!latex.contains("PuzzleClues"));
+    }
+}
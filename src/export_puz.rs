@@ -0,0 +1,220 @@
+use std::io::{self, Write};
+
+use crate::import_puz::ImportedPuz;
+
+/// Writes `puz` in the Across Lite .puz format, recomputing all checksums.
+/// See https://gist.github.com/sliminality/dab21fa834eae0a70193c7cd69c356d5 for the layout.
+pub fn write_puzzle<W: Write>(puz: &ImportedPuz, f: &mut W) -> io::Result<()> {
+    let header = puz.header();
+    // the format stores the dimensions in single bytes
+    if puz.width() > u8::MAX as usize || puz.height() > u8::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!(".puz grids are at most 255x255, this one is {}x{}", puz.width(), puz.height())))
+    }
+
+    let solution = grid_bytes(puz, |r_idx, c_idx| puz.solution_at(r_idx, c_idx));
+    let player_state = grid_bytes(puz, |r_idx, c_idx| puz.player_state_at(r_idx, c_idx));
+
+    let mut cib = Vec::with_capacity(8);
+    cib.push(puz.width() as u8);
+    cib.push(puz.height() as u8);
+    cib.extend((puz.clues().len() as u16).to_le_bytes());
+    cib.extend(header.unk_bitmask.to_le_bytes());
+    cib.extend(header.scrambled_tag.to_le_bytes());
+
+    let cib_cksum = cksum_region(&cib, 0);
+    let sol_cksum = cksum_region(&solution, 0);
+    let grid_cksum = cksum_region(&player_state, 0);
+    let text_cksum = cksum_strings(puz, 0);
+
+    let mut cksum = cksum_region(&solution, cib_cksum);
+    cksum = cksum_region(&player_state, cksum);
+    cksum = cksum_strings(puz, cksum);
+
+    let masked_low = [
+        0x49 ^ (cib_cksum & 0xff) as u8,
+        0x43 ^ (sol_cksum & 0xff) as u8,
+        0x48 ^ (grid_cksum & 0xff) as u8,
+        0x45 ^ (text_cksum & 0xff) as u8,
+    ];
+    let masked_high = [
+        0x41 ^ (cib_cksum >> 8) as u8,
+        0x54 ^ (sol_cksum >> 8) as u8,
+        0x45 ^ (grid_cksum >> 8) as u8,
+        0x44 ^ (text_cksum >> 8) as u8,
+    ];
+
+    f.write_all(&cksum.to_le_bytes())?;
+    f.write_all(&fixed_len_latin1(&header.file_magic, 0xc, "ACROSS&DOWN\0"))?;
+    f.write_all(&cib_cksum.to_le_bytes())?;
+    f.write_all(&masked_low)?;
+    f.write_all(&masked_high)?;
+    f.write_all(&fixed_len_latin1(&header.ver_str, 0x4, "1.3\0"))?;
+    f.write_all(&header.reserved_1c)?;
+    f.write_all(&header.scrambled_cksum.to_le_bytes())?;
+    f.write_all(&[0u8; 0xc])?;
+    f.write_all(&cib)?;
+
+    f.write_all(&solution)?;
+    f.write_all(&player_state)?;
+
+    for st in [puz.title(), puz.author(), puz.copyright()] {
+        f.write_all(&latin1_bytes(st))?;
+        f.write_all(&[0])?;
+    }
+    for clue in puz.clues() {
+        f.write_all(&latin1_bytes(clue))?;
+        f.write_all(&[0])?;
+    }
+    f.write_all(&latin1_bytes(puz.notes()))?;
+    f.write_all(&[0])?;
+
+    for ext in puz.extensions() {
+        f.write_all(&fixed_len_latin1(ext.title(), 4, "    "))?;
+        f.write_all(&(ext.data().len() as u16).to_le_bytes())?;
+        f.write_all(&cksum_region(ext.data(), 0).to_le_bytes())?;
+        f.write_all(ext.data())?;
+        f.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+fn grid_bytes<F: Fn(usize, usize) -> char>(puz: &ImportedPuz, char_at: F) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(puz.width() * puz.height());
+    for r_idx in 0..puz.height() {
+        for c_idx in 0..puz.width() {
+            ret.push(latin1_byte(char_at(r_idx, c_idx)))
+        }
+    }
+    ret
+}
+
+/// Checksum of the strings section: title, author, copyright and notes count with
+/// their terminating NUL (and only when not empty), clues without it.
+fn cksum_strings(puz: &ImportedPuz, mut cksum: u16) -> u16 {
+    for st in [puz.title(), puz.author(), puz.copyright()] {
+        if !st.is_empty() {
+            cksum = cksum_region(&latin1_bytes(st), cksum);
+            cksum = cksum_region(&[0], cksum);
+        }
+    }
+    for clue in puz.clues() {
+        cksum = cksum_region(&latin1_bytes(clue), cksum);
+    }
+    if !puz.notes().is_empty() {
+        cksum = cksum_region(&latin1_bytes(puz.notes()), cksum);
+        cksum = cksum_region(&[0], cksum);
+    }
+    cksum
+}
+
+pub fn cksum_region(data: &[u8], mut cksum: u16) -> u16 {
+    for by in data {
+        cksum = cksum.rotate_right(1);
+        cksum = cksum.wrapping_add(*by as u16);
+    }
+    cksum
+}
+
+fn latin1_byte(ch: char) -> u8 {
+    u8::try_from(ch).unwrap_or(b'?')
+}
+
+fn latin1_bytes(st: &str) -> Vec<u8> {
+    st.chars().map(latin1_byte).collect()
+}
+
+/// `st` as exactly `len` latin1 bytes, `default` if `st` doesn't fit
+fn fixed_len_latin1(st: &str, len: usize, default: &str) -> Vec<u8> {
+    let bytes = latin1_bytes(st);
+    if bytes.len() == len {
+        bytes
+    } else {
+        latin1_bytes(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::api_types::TypingDir;
+    use crate::import_puz::{import_puzzle, PuzStrings, GEXT_CIRCLED, GEXT_REVEALED};
+
+    /// 2x2 grid, all open: 1A, 1D, 2D and 3A
+    fn small_puzzle() -> ImportedPuz {
+        let clues = BTreeMap::from([
+            ((1, TypingDir::Across), "ab".to_string()),
+            ((1, TypingDir::Down), "ac".to_string()),
+            ((2, TypingDir::Down), "bd".to_string()),
+            ((3, TypingDir::Across), "cd".to_string()),
+        ]);
+        let strings = PuzStrings::new("T".to_string(), "Au".to_string(), String::new(), String::new());
+        ImportedPuz::from_grid(
+            vec![vec!['A', 'B'], vec!['C', 'D']],
+            vec![vec!['A', '-'], vec!['-', '-']],
+            strings,
+            &clues,
+            Vec::new(),
+        )
+    }
+
+    fn write_to_vec(puz: &ImportedPuz) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_puzzle(puz, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut puz = small_puzzle();
+        puz.set_markup_at(0, 0, GEXT_CIRCLED);
+        puz.set_markup_at(1, 1, GEXT_REVEALED);
+
+        let read_back = import_puzzle(&mut Cursor::new(write_to_vec(&puz))).unwrap();
+
+        assert_eq!((read_back.width(), read_back.height()), (2, 2));
+        for r_idx in 0..2 {
+            for c_idx in 0..2 {
+                assert_eq!(read_back.solution_at(r_idx, c_idx), puz.solution_at(r_idx, c_idx));
+                assert_eq!(read_back.player_state_at(r_idx, c_idx), puz.player_state_at(r_idx, c_idx));
+                assert_eq!(read_back.markup_at(r_idx, c_idx), puz.markup_at(r_idx, c_idx));
+            }
+        }
+        assert_eq!(read_back.clues(), &vec!["ab", "ac", "bd", "cd"]);
+        assert_eq!((read_back.title(), read_back.author()), ("T", "Au"));
+    }
+
+    // expected values worked out separately from the published checksum algorithm
+    #[test]
+    fn checksums() {
+        let mut puz = small_puzzle();
+        puz.set_markup_at(0, 0, GEXT_CIRCLED);
+        puz.set_markup_at(1, 1, GEXT_REVEALED);
+        let bytes = write_to_vec(&puz);
+
+        assert_eq!(bytes[0..2], 0xa71c_u16.to_le_bytes());
+        assert_eq!(bytes[0xe..0x10], 0x2c00_u16.to_le_bytes());
+        assert_eq!(bytes[0x10..0x14], [0x49, 0x3d, 0x1e, 0x83]);
+        assert_eq!(bytes[0x14..0x18], [0x6d, 0x74, 0xa5, 0xd6]);
+
+        let gext = bytes.windows(4).position(|window| window == b"GEXT").unwrap();
+        assert_eq!(bytes[gext + 4..gext + 8], [4, 0, 0x50, 0]);
+    }
+
+    #[test]
+    fn rejects_grids_wider_than_a_byte() {
+        let puz = ImportedPuz::from_grid(
+            vec![vec!['A'; 256]],
+            vec![vec!['-'; 256]],
+            PuzStrings::default(),
+            &BTreeMap::new(),
+            Vec::new(),
+        );
+        let err = write_puzzle(&puz, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::api_types::{Pos, TypingDir};
+use crate::state::{Cell, CellMarks};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CellChange {
+    pub pos: Pos,
+    pub before: Cell,
+    pub after: Cell,
+    pub before_marks: CellMarks,
+    pub after_marks: CellMarks,
+}
+
+/// One undoable step, possibly touching several cells (e.g. clearing a word)
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub changes: Vec<CellChange>,
+    // cursor when the edit was made, restored on undo / redo
+    pub cur_pos: Pos,
+    pub cur_dir: TypingDir,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl History {
+    /// Records a new edit, which drops whatever could be redone
+    pub fn record(&mut self, edit: Edit) {
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Edit> {
+        let edit = self.undo_stack.pop()?;
+        self.redo_stack.push(edit.clone());
+        Some(edit)
+    }
+
+    pub fn redo(&mut self) -> Option<Edit> {
+        let edit = self.redo_stack.pop()?;
+        self.undo_stack.push(edit.clone());
+        Some(edit)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
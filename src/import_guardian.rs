@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+
+use serde_json::Value;
+
+use crate::api_types::{Pos, TypingDir};
+use crate::import_puz::{format_date, ClueGroups, ImportedPuz, PuzStrings};
+use crate::util::invalid_data;
+
+/// Imports a crossword saved as JSON in the Guardian's crossword schema.
+/// Entries linked through `group` become a single multi-part clue. Prize puzzles come
+/// without a `solution`; their answers are left unknown ('-').
+pub fn import_guardian<R: Read>(f: &mut R) -> io::Result<ImportedPuz> {
+    let root: Value = serde_json::from_reader(f).map_err(invalid_data)?;
+
+    let width = root["dimensions"]["cols"].as_u64().unwrap_or(0) as usize;
+    let height = root["dimensions"]["rows"].as_u64().unwrap_or(0) as usize;
+    if width == 0 || height == 0 {
+        return Err(invalid_data("guardian: missing or empty dimensions"))
+    }
+
+    let entries = root["entries"].as_array()
+        .ok_or_else(|| invalid_data("guardian: no entries"))?;
+
+    let mut solution = vec![vec!['.'; width]; height];
+    let mut id_2_entry: BTreeMap<&str, (Pos, TypingDir)> = BTreeMap::new();
+    let mut numbered_clues = BTreeMap::new();
+
+    for entry in entries {
+        let (Some(id), Some(num), Some(dir), Some(col), Some(row)) = (
+            entry["id"].as_str(),
+            entry["number"].as_u64(),
+            parse_direction(&entry["direction"]),
+            entry["position"]["x"].as_u64(),
+            entry["position"]["y"].as_u64(),
+        ) else {
+            return Err(invalid_data(format!("guardian: malformed entry {entry}")))
+        };
+
+        let start = Pos::new(row as usize, col as usize);
+        let length = entry["length"].as_u64().unwrap_or(0) as usize;
+        let mut letters = entry["solution"].as_str().unwrap_or("").chars();
+
+        for offset in 0..length {
+            let pos = match dir {
+                TypingDir::Across => Pos::new(start.row, start.col + offset),
+                TypingDir::Down => Pos::new(start.row + offset, start.col),
+            };
+            if pos.row >= height || pos.col >= width {
+                return Err(invalid_data(format!("guardian: entry {id} runs off the grid")))
+            }
+            solution[pos.row][pos.col] = letters.next().unwrap_or('-').to_ascii_uppercase();
+        }
+
+        id_2_entry.insert(id, (start, dir));
+        numbered_clues.insert((num as usize, dir), entry["clue"].as_str().unwrap_or("").to_string());
+    }
+
+    let mut groups = Vec::new();
+    for entry in entries {
+        let Some(group) = entry["group"].as_array() else {
+            continue
+        };
+        // Only the first entry of a group creates it
+        if group.len() < 2 || group[0].as_str() != entry["id"].as_str() {
+            continue
+        }
+
+        let linked = group.iter()
+            .filter_map(|id| id.as_str().and_then(|id| id_2_entry.get(id)).cloned())
+            .collect();
+        groups.push(linked)
+    }
+
+    let player_state = solution.iter()
+        .map(|row| row.iter().map(|ch| if *ch == '.' { '.' } else { '-' }).collect())
+        .collect();
+
+    let strings = PuzStrings::new(
+        root["name"].as_str().unwrap_or("").to_string(),
+        root["creator"]["name"].as_str().unwrap_or("").to_string(),
+        String::new(),
+        String::new(),
+    );
+
+    // milliseconds since the epoch
+    let date = match &root["date"] {
+        Value::Number(millis) => millis.as_i64().map(|millis| format_date(millis.div_euclid(86_400_000))),
+        Value::String(date) => Some(date.clone()),
+        _ => None
+    };
+
+    let puz = ImportedPuz::from_grid(solution, player_state, strings, &numbered_clues, Vec::new());
+    Ok(puz.with_clue_groups(ClueGroups(groups)).with_date(date.unwrap_or_default()))
+}
+
+fn parse_direction(value: &Value) -> Option<TypingDir> {
+    match value.as_str()? {
+        "across" => Some(TypingDir::Across),
+        "down" => Some(TypingDir::Down),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// C A T
+    /// A # E
+    /// B E D
+    /// with 1 Down and 2 Down linked into one clue
+    const TINY: &str = r#"{
+        "name": "Cryptic crossword No 1",
+        "creator": {"name": "Setter"},
+        "date": 1709769600000,
+        "dimensions": {"cols": 3, "rows": 3},
+        "entries": [
+            {"id": "1-across", "number": 1, "direction": "across", "position": {"x": 0, "y": 0},
+             "length": 3, "solution": "cat", "clue": "Pet (3)", "group": ["1-across"]},
+            {"id": "1-down", "number": 1, "direction": "down", "position": {"x": 0, "y": 0},
+             "length": 3, "solution": "CAB", "clue": "Taxi and talk (3,3)", "group": ["1-down", "2-down"]},
+            {"id": "2-down", "number": 2, "direction": "down", "position": {"x": 2, "y": 0},
+             "length": 3, "solution": "TED", "clue": "See 1", "group": ["1-down", "2-down"]},
+            {"id": "3-across", "number": 3, "direction": "across", "position": {"x": 0, "y": 2},
+             "length": 3, "solution": "BED", "clue": "Bunk (3)", "group": ["3-across"]}
+        ]
+    }"#;
+
+    fn import(text: &str) -> io::Result<ImportedPuz> {
+        import_guardian(&mut Cursor::new(text))
+    }
+
+    #[test]
+    fn grid_clues_and_metadata() {
+        let puz = import(TINY).unwrap();
+
+        assert_eq!((puz.width(), puz.height()), (3, 3));
+        assert_eq!((puz.solution_at(0, 0), puz.solution_at(1, 1), puz.solution_at(2, 2)), ('C', '.', 'D'));
+        assert_eq!(puz.player_state_at(0, 0), '-');
+        assert_eq!(puz.clues(), &vec!["Pet (3)", "Taxi and talk (3,3)", "See 1", "Bunk (3)"]);
+        assert_eq!((puz.title(), puz.author(), puz.date()), ("Cryptic crossword No 1", "Setter", "2024-03-07"));
+    }
+
+    #[test]
+    fn grouped_entries_become_one_clue() {
+        let puz = import(TINY).unwrap();
+
+        assert_eq!(puz.clue_groups.0, vec![vec![
+            (Pos::new(0, 0), TypingDir::Down),
+            (Pos::new(0, 2), TypingDir::Down),
+        ]]);
+    }
+
+    #[test]
+    fn prize_puzzles_have_unknown_answers() {
+        let puz = import(r#"{
+            "dimensions": {"cols": 2, "rows": 1},
+            "entries": [{"id": "1-across", "number": 1, "direction": "across",
+                         "position": {"x": 0, "y": 0}, "length": 2, "clue": "Hidden (2)"}]
+        }"#).unwrap();
+
+        assert_eq!((puz.solution_at(0, 0), puz.solution_at(0, 1)), ('-', '-'));
+    }
+
+    #[test]
+    fn rejects_entries_off_the_grid() {
+        let err = import(r#"{
+            "dimensions": {"cols": 2, "rows": 1},
+            "entries": [{"id": "1-across", "number": 1, "direction": "across",
+                         "position": {"x": 1, "y": 0}, "length": 2, "clue": "Off (2)"}]
+        }"#).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
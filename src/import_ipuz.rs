@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+
+use serde_json::Value;
+
+use crate::api_types::{Pos, TypingDir};
+use crate::import_puz::{Extension, ImportedPuz, Marker, PuzStrings, Side, GEXT_CIRCLED};
+use crate::util::invalid_data;
+
+/// Imports a crossword in the (JSON based) ipuz format, see http://www.ipuz.org/
+pub fn import_ipuz<R: Read>(f: &mut R) -> io::Result<ImportedPuz> {
+    let mut text = String::new();
+    f.read_to_string(&mut text)?;
+
+    let root = parse_ipuz_json(&text)?;
+
+    let width = root["dimensions"]["width"].as_u64().unwrap_or(0) as usize;
+    let height = root["dimensions"]["height"].as_u64().unwrap_or(0) as usize;
+    if width == 0 || height == 0 {
+        return Err(invalid_data("ipuz: missing or empty dimensions"))
+    }
+
+    let block = root["block"].as_str().unwrap_or("#");
+    let mut solution = vec![vec!['.'; width]; height];
+    let mut player_state = vec![vec!['.'; width]; height];
+    let mut markup = vec![0u8; width * height];
+    let mut markers = BTreeMap::new();
+
+    for r_idx in 0..height {
+        for c_idx in 0..width {
+            let puzzle_cell = &root["puzzle"][r_idx][c_idx];
+            if puzzle_cell.is_null() || cell_value(puzzle_cell, "cell") == Some(block.to_string()) {
+                continue
+            }
+
+            if puzzle_cell["style"]["shapebg"].as_str() == Some("circle") {
+                markup[r_idx * width + c_idx] |= GEXT_CIRCLED
+            }
+            let cell_markers = style_markers(&puzzle_cell["style"]);
+            if !cell_markers.is_empty() {
+                markers.insert(Pos::new(r_idx, c_idx), cell_markers);
+            }
+
+            let letter = cell_value(&root["solution"][r_idx][c_idx], "value")
+                .and_then(|value| value.chars().next())
+                .unwrap_or('-');
+            solution[r_idx][c_idx] = letter.to_ascii_uppercase();
+
+            let saved = cell_value(&root["saved"][r_idx][c_idx], "value")
+                .and_then(|value| value.chars().next())
+                .filter(|ch| ch.is_alphanumeric());
+            player_state[r_idx][c_idx] = saved.map(|ch| ch.to_ascii_uppercase()).unwrap_or('-');
+        }
+    }
+
+    let mut numbered_clues = BTreeMap::new();
+    for (key, dir) in [("Across", TypingDir::Across), ("Down", TypingDir::Down)] {
+        let clue_list = root["clues"].as_object()
+            .and_then(|clues| clues.iter().find(|(name, _)| name.split(':').next() == Some(key)))
+            .and_then(|(_, clue_list)| clue_list.as_array());
+
+        for clue in clue_list.into_iter().flatten() {
+            if let Some((num, text)) = parse_clue(clue) {
+                numbered_clues.insert((num, dir), text);
+            }
+        }
+    }
+
+    let strings = PuzStrings::new(
+        string_field(&root, "title"),
+        string_field(&root, "author"),
+        string_field(&root, "copyright"),
+        string_field(&root, "notes"),
+    );
+
+    let extensions = if markup.iter().any(|bits| *bits != 0) {
+        vec![Extension::new("GEXT", markup)]
+    } else {
+        Vec::new()
+    };
+
+    Ok(ImportedPuz::from_grid(solution, player_state, strings, &numbered_clues, extensions)
+        .with_markers(markers)
+        .with_date(parse_date(&root["date"])))
+}
+
+fn parse_ipuz_json(text: &str) -> io::Result<Value> {
+    // Files are sometimes served wrapped as JSONP: ipuz({...})
+    let json_text = text.trim();
+    let json_text = json_text.strip_prefix("ipuz(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(json_text);
+
+    serde_json::from_str(json_text).map_err(invalid_data)
+}
+
+/// A cell is either a bare value or an object holding it under `key`
+fn cell_value(cell: &Value, key: &str) -> Option<String> {
+    let value = if cell.is_object() { &cell[key] } else { cell };
+
+    match value {
+        Value::String(st) => Some(st.clone()),
+        Value::Number(num) => Some(num.to_string()),
+        _ => None
+    }
+}
+
+/// Bars from `"barred": "TRBL"` and triangles from `"shapebg": "triangle-up"` and the like
+fn style_markers(style: &Value) -> Vec<Marker> {
+    let mut markers: Vec<Marker> = style["barred"].as_str().unwrap_or("").chars()
+        .filter_map(|ch| match ch.to_ascii_uppercase() {
+            'T' => Some(Side::Top),
+            'R' => Some(Side::Right),
+            'B' => Some(Side::Bottom),
+            'L' => Some(Side::Left),
+            _ => None
+        })
+        .map(Marker::Bar)
+        .collect();
+
+    let triangle = match style["shapebg"].as_str() {
+        Some("triangle-up") => Some(Side::Top),
+        Some("triangle-right") => Some(Side::Right),
+        Some("triangle-down") => Some(Side::Bottom),
+        Some("triangle-left") => Some(Side::Left),
+        _ => None
+    };
+    markers.extend(triangle.map(Marker::Triangle));
+    markers
+}
+
+/// Clues are either `[num, "text"]`, `"num text"` or `{"number": num, "clue": "text"}`
+fn parse_clue(clue: &Value) -> Option<(usize, String)> {
+    match clue {
+        Value::Array(parts) => {
+            let num = parts.first().and_then(json_number)?;
+            let text = parts.get(1)?.as_str()?.to_string();
+            Some((num, text))
+        },
+        Value::Object(obj) => {
+            let num = obj.get("number").and_then(json_number)?;
+            let text = obj.get("clue")?.as_str()?.to_string();
+            Some((num, text))
+        },
+        Value::String(st) => {
+            let (num, text) = st.split_once(' ')?;
+            Some((num.parse().ok()?, text.to_string()))
+        }
+        _ => None
+    }
+}
+
+fn json_number(value: &Value) -> Option<usize> {
+    match value {
+        Value::Number(num) => num.as_u64().map(|num| num as usize),
+        Value::String(st) => st.parse().ok(),
+        _ => None
+    }
+}
+
+/// ipuz dates are "mm/dd/yyyy", other forms are kept as they are
+fn parse_date(value: &Value) -> String {
+    let date = value.as_str().unwrap_or("").trim();
+    let parts: Vec<Option<u32>> = date.split('/').map(|part| part.trim().parse().ok()).collect();
+    match parts[..] {
+        [Some(month), Some(day), Some(year)] => format!("{year:04}-{month:02}-{day:02}"),
+        _ => date.to_string()
+    }
+}
+
+fn string_field(root: &Value, key: &str) -> String {
+    root[key].as_str().unwrap_or("").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn import(text: &str) -> io::Result<ImportedPuz> {
+        import_ipuz(&mut Cursor::new(text))
+    }
+
+    #[test]
+    fn grid_clues_and_metadata() {
+        let puz = import(r##"ipuz({
+            "version": "http://ipuz.org/v2",
+            "kind": ["http://ipuz.org/crossword#1"],
+            "title": "Tiny", "author": "Someone", "date": "3/7/2024",
+            "dimensions": {"width": 3, "height": 3},
+            "puzzle": [[1, 0, 2], [0, "#", 0], [3, 0, 0]],
+            "solution": [["c", "A", "T"], ["A", "#", "E"], ["B", "E", "D"]],
+            "saved": [["C", "", ""], ["", "#", ""], ["", "", "x"]],
+            "clues": {
+                "Across": [[1, "Pet"], {"number": 3, "clue": "Bunk"}],
+                "Down:Down": ["1 Taxi", [2, "Talk"]]
+            }
+        })"##).unwrap();
+
+        assert_eq!((puz.width(), puz.height()), (3, 3));
+        assert_eq!((puz.solution_at(0, 0), puz.solution_at(1, 1), puz.solution_at(2, 2)), ('C', '.', 'D'));
+        assert_eq!((puz.player_state_at(0, 0), puz.player_state_at(0, 1), puz.player_state_at(2, 2)), ('C', '-', 'X'));
+        assert_eq!(puz.clues(), &vec!["Pet", "Taxi", "Talk", "Bunk"]);
+        assert_eq!((puz.title(), puz.author(), puz.date()), ("Tiny", "Someone", "2024-03-07"));
+    }
+
+    #[test]
+    fn null_and_omitted_cells_are_blocks() {
+        let puz = import(r#"{
+            "dimensions": {"width": 3, "height": 2},
+            "block": "X",
+            "puzzle": [[0, null, "X"], [0, 0]],
+            "solution": [["A", null, null], ["B", "C"]]
+        }"#).unwrap();
+
+        assert_eq!(puz.solution_at(0, 0), 'A');
+        assert_eq!((puz.solution_at(0, 1), puz.solution_at(0, 2), puz.solution_at(1, 2)), ('.', '.', '.'));
+        assert_eq!(puz.player_state_at(0, 1), '.');
+    }
+
+    #[test]
+    fn missing_solution_leaves_answers_unknown() {
+        let puz = import(r#"{
+            "dimensions": {"width": 2, "height": 1},
+            "puzzle": [[1, 0]]
+        }"#).unwrap();
+
+        assert_eq!((puz.solution_at(0, 0), puz.solution_at(0, 1)), ('-', '-'));
+    }
+
+    #[test]
+    fn circles_bars_and_triangles() {
+        let puz = import(r#"{
+            "dimensions": {"width": 3, "height": 1},
+            "puzzle": [[
+                {"cell": 1, "style": {"shapebg": "circle"}},
+                {"cell": 0, "style": {"barred": "RB"}},
+                {"cell": 0, "style": {"shapebg": "triangle-left"}}
+            ]],
+            "solution": [["A", "B", "C"]]
+        }"#).unwrap();
+
+        assert_eq!(puz.markers_at(0, 0), vec![Marker::Circle]);
+        assert_eq!(puz.markers_at(0, 1), vec![Marker::Bar(Side::Right), Marker::Bar(Side::Bottom)]);
+        assert_eq!(puz.markers_at(0, 2), vec![Marker::Triangle(Side::Left)]);
+    }
+
+    #[test]
+    fn rejects_missing_dimensions() {
+        let err = import(r#"{"puzzle": [[0]]}"#).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -2,44 +2,69 @@ use std::collections::{BTreeSet, BTreeMap};
 use std::io::{self, BufRead};
 use std::{
     collections::VecDeque,
-    fs::File,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{Read, Seek, SeekFrom},
 };
 
 use crate::api_types::{Pos, TypingDir};
+use crate::util::invalid_data;
 
-#[derive(Debug, Default)]
+/// The fixed part of a .puz file. The file and CIB checksums are not kept: they
+/// are recomputed whenever the puzzle is written back.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
-    cksum: u16, // len 2
     // file_magic: [u8; 0xc], // len 0xC = 12
-    file_magic: String,
-    cib_cksum: u16, // len 2
-    masked_low_cksums: [u8; 4],
-    masked_high_cksums: [u8; 4],
-
-    ver_str: String, // len 4
-    reserved_1c: [u8; 2],
-    scrambled_cksum: u16,
-    width: usize,
-    height: usize,
-    num_clues: usize, // size 2
-    unk_bitmask: u16,
-    scrambled_tag: u16,
+    pub(crate) file_magic: String,
+    pub(crate) masked_low_cksums: [u8; 4],
+    pub(crate) masked_high_cksums: [u8; 4],
+
+    pub(crate) ver_str: String, // len 4
+    pub(crate) reserved_1c: [u8; 2],
+    pub(crate) scrambled_cksum: u16,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) num_clues: usize, // size 2
+    pub(crate) unk_bitmask: u16,
+    pub(crate) scrambled_tag: u16,
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CellNumbers {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::map_as_seq"))]
     cell_2_num: BTreeMap<Pos, usize>,
     across_numbers: BTreeSet<usize>,
     down_numbers: BTreeSet<usize>,
 }
 
-#[derive(Debug, Default)]
-pub struct Pos2ClueIdx(pub BTreeMap<(Pos, TypingDir), usize>);
+impl CellNumbers {
+    pub fn number_at(&self, pos: &Pos) -> Option<usize> {
+        self.cell_2_num.get(pos).cloned()
+    }
+}
 
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pos2ClueIdx(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::map_as_seq"))]
+    pub BTreeMap<(Pos, TypingDir), usize>
+);
+
+/// Entries linked into a single multi-part clue, e.g. "5,14 across". Each group lists the
+/// (start pos, direction) of its entries in clue order; the first one holds the clue text.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClueGroups(pub Vec<Vec<(Pos, TypingDir)>>);
+
+impl ClueGroups {
+    pub fn group_of(&self, entry: &(Pos, TypingDir)) -> Option<&Vec<(Pos, TypingDir)>> {
+        self.0.iter().find(|group| group.contains(entry))
+    }
+}
 
 #[allow(unused)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoardContents {
     data: Vec<Vec<char>>,
 }
@@ -121,16 +146,14 @@ impl CellNumbers{
         while !all_numbers.is_empty() {
             let num = all_numbers.pop_first().unwrap();
             if self.across_numbers.contains(&num) {
-                let pos = num_2_pos.get(&num).expect(format!("No pos for num={num}").as_str());
+                let pos = num_2_pos.get(&num).unwrap_or_else(|| panic!("No pos for num={num}"));
                 ret.insert( (*pos, TypingDir::Across), clue_idx );
-                println!("Assigned num: {num}, dir: across  clue_idx: {clue_idx}");
                 clue_idx += 1 
             }
             
             if self.down_numbers.contains(&num) {
-                let pos = num_2_pos.get(&num).expect(format!("No pos for num={num}").as_str());
+                let pos = num_2_pos.get(&num).unwrap_or_else(|| panic!("No pos for num={num}"));
                 ret.insert( (*pos, TypingDir::Down), clue_idx );
-                println!("Assigned num: {num}, dir: down  clue_idx: {clue_idx}");
                 clue_idx += 1
             }
         }
@@ -141,7 +164,8 @@ impl CellNumbers{
 
 
 #[allow(unused)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PuzStrings {
     title: String,
     author: String,
@@ -150,17 +174,139 @@ pub struct PuzStrings {
     notes: String,
 }
 
+impl PuzStrings {
+    pub fn new(title: String, author: String, copyright: String, notes: String) -> Self {
+        PuzStrings { title, author, copyright, clues: Vec::new(), notes }
+    }
+}
+
+// Bits of a GEXT (markup) board cell
+pub const GEXT_PREV_INCORRECT: u8 = 0x10;
+pub const GEXT_INCORRECT: u8 = 0x20;
+pub const GEXT_REVEALED: u8 = 0x40;
+pub const GEXT_CIRCLED: u8 = 0x80;
+
+/// Side of a cell that a bar or triangle marker is drawn against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/// Decoration drawn in a cell besides its letter and clue number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Marker {
+    Circle,
+    /// Thick border between two cells, as in barred grids
+    Bar(Side),
+    /// Small triangle pointing at a side of the cell
+    Triangle(Side),
+}
+
+/// Extra section found after the strings, e.g. GEXT, LTIM, RTBL
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Extension {
+    title: String,
+    data: Vec<u8>,
+}
+
+impl Extension {
+    pub fn new(title: &str, data: Vec<u8>) -> Self {
+        Extension { title: title.to_string(), data }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
 #[allow(unused)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImportedPuz {
     header: Header,
     solution: BoardContents,
     player_state: BoardContents,
     strings: PuzStrings,
+    extensions: Vec<Extension>,
     pub pos_2_clue_idx: Pos2ClueIdx, 
+    pub clue_groups: ClueGroups,
+    /// Bars and triangles, which .puz files cannot hold; circles are kept in GEXT
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::map_as_seq"))]
+    markers: BTreeMap<Pos, Vec<Marker>>,
+    /// Publication date as YYYY-MM-DD, empty when the file doesn't give one
+    date: String,
 }
 
 impl ImportedPuz {
+    /// Builds a puzzle from a grid decoded from another format. `solution` and `player_state`
+    /// use the .puz conventions ('.' for blocks, '-' for empty squares) and `numbered_clues`
+    /// is keyed by (clue number, direction); clues get stored in .puz order.
+    pub fn from_grid(
+        solution: Vec<Vec<char>>,
+        player_state: Vec<Vec<char>>,
+        mut strings: PuzStrings,
+        numbered_clues: &BTreeMap<(usize, TypingDir), String>,
+        extensions: Vec<Extension>) -> Self {
+        let solution = BoardContents { data: solution };
+        let cell_numbers = solution.calc_cell_numbers();
+        let pos_2_clue_idx = cell_numbers.calc_num_2_clue_idx();
+
+        let mut clues = vec![String::new(); pos_2_clue_idx.0.len()];
+        for ((pos, dir), clue_idx) in pos_2_clue_idx.0.iter() {
+            if let Some(clue) = cell_numbers.number_at(pos)
+                .and_then(|num| numbered_clues.get(&(num, *dir))) {
+                clues[*clue_idx] = clue.clone()
+            }
+        }
+        strings.clues = clues;
+
+        let header = Header {
+            file_magic: "ACROSS&DOWN\0".to_string(),
+            ver_str: "1.3\0".to_string(),
+            width: solution.width(),
+            height: solution.height(),
+            num_clues: strings.clues.len(),
+            ..Default::default()
+        };
+
+        ImportedPuz {
+            header,
+            solution,
+            player_state: BoardContents { data: player_state },
+            strings,
+            extensions,
+            pos_2_clue_idx,
+            clue_groups: ClueGroups::default(),
+            markers: BTreeMap::new(),
+            date: String::new(),
+        }
+    }
+
+    pub fn with_clue_groups(mut self, clue_groups: ClueGroups) -> Self {
+        self.clue_groups = clue_groups;
+        self
+    }
+
+    pub fn with_markers(mut self, markers: BTreeMap<Pos, Vec<Marker>>) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    pub fn with_date(mut self, date: String) -> Self {
+        self.date = date;
+        self
+    }
+
     pub fn width(&self) -> usize {
         self.header.width
     }
@@ -173,22 +319,101 @@ impl ImportedPuz {
         self.solution.data[r_idx][c_idx]
     }
     
+    pub fn player_state_at(&self, r_idx: usize, c_idx: usize) -> char {
+        self.player_state.data[r_idx][c_idx]
+    }
+
+    pub fn set_player_state_at(&mut self, r_idx: usize, c_idx: usize, ch: char) {
+        self.player_state.data[r_idx][c_idx] = ch
+    }
+
     pub fn clues(&self) -> &Vec<String> {
         &self.strings.clues
     }
+
+    pub fn notes(&self) -> &str {
+        &self.strings.notes
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    pub fn title(&self) -> &str {
+        &self.strings.title
+    }
+
+    pub fn author(&self) -> &str {
+        &self.strings.author
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    pub fn copyright(&self) -> &str {
+        &self.strings.copyright
+    }
+
+    pub fn cell_numbers(&self) -> CellNumbers {
+        self.solution.calc_cell_numbers()
+    }
+
+    /// GEXT bits for a cell, 0 if the puzzle has no GEXT section
+    pub fn markup_at(&self, r_idx: usize, c_idx: usize) -> u8 {
+        self.extension("GEXT")
+            .and_then(|data| data.get(r_idx * self.width() + c_idx).cloned())
+            .unwrap_or(0)
+    }
+
+    /// Sets the GEXT bits of a cell, adding a GEXT section if there is none
+    pub fn set_markup_at(&mut self, r_idx: usize, c_idx: usize, bits: u8) {
+        let size = self.width() * self.height();
+        let idx = r_idx * self.width() + c_idx;
+
+        match self.extensions.iter_mut().find(|ext| ext.title == "GEXT") {
+            Some(ext) => ext.data[idx] = bits,
+            None => {
+                let mut data = vec![0u8; size];
+                data[idx] = bits;
+                self.extensions.push(Extension::new("GEXT", data))
+            }
+        }
+    }
+
+    pub fn is_circled(&self, r_idx: usize, c_idx: usize) -> bool {
+        self.markup_at(r_idx, c_idx) & GEXT_CIRCLED != 0
+    }
+
+    /// Every marker of a cell, circles included
+    pub fn markers_at(&self, r_idx: usize, c_idx: usize) -> Vec<Marker> {
+        let mut markers = Vec::new();
+        if self.is_circled(r_idx, c_idx) {
+            markers.push(Marker::Circle)
+        }
+        if let Some(extra) = self.markers.get(&Pos::new(r_idx, c_idx)) {
+            markers.extend(extra)
+        }
+        markers
+    }
+
+    fn extension(&self, title: &str) -> Option<&Vec<u8>> {
+        self.extensions.iter()
+            .find(|ext| ext.title == title)
+            .map(|ext| &ext.data)
+    }
 }
 
-pub fn import_puzzle(f: &mut BufReader<File>) -> io::Result<ImportedPuz> {
+pub fn import_puzzle<R: BufRead + Seek>(f: &mut R) -> io::Result<ImportedPuz> {
     let header = read_header(f)?;
     let (width, height, n_clues) = (header.width, header.height, header.num_clues);
     let solution = read_contents(f, height, width)?;
 
-    for r_idx in 0..height {
-        println!("row {:02} : {s:?}", r_idx, s=solution.data[r_idx]);
-    }
-
     let pos_2_num: CellNumbers = solution.calc_cell_numbers();
-    println!("pos_2_num: {pos_2_num:?}");
     let pos_2_clue_idx = pos_2_num.calc_num_2_clue_idx();
     
     Ok(ImportedPuz {
@@ -196,16 +421,21 @@ pub fn import_puzzle(f: &mut BufReader<File>) -> io::Result<ImportedPuz> {
         solution,
         player_state: read_contents(f, height, width)?,
         strings: read_strings(f, n_clues)?,
-        pos_2_clue_idx
+        extensions: read_extensions(f)?,
+        pos_2_clue_idx,
+        clue_groups: ClueGroups::default(),
+        markers: BTreeMap::new(),
+        date: String::new(),
     })
 }
 
-pub fn read_header(f: &mut BufReader<File>) -> io::Result<Header> {
-    let mut header = Header::default();
-
-    header.cksum = read_u16(f)?;
-    header.file_magic = read_latin1_string(f, 0xc)?;
-    header.cib_cksum = read_u16(f)?;
+pub fn read_header<R: BufRead + Seek>(f: &mut R) -> io::Result<Header> {
+    let _cksum = read_u16(f)?;
+    let mut header = Header {
+        file_magic: read_latin1_string(f, 0xc)?,
+        ..Default::default()
+    };
+    let _cib_cksum = read_u16(f)?;
 
     f.read_exact(&mut header.masked_low_cksums)?;
     f.read_exact(&mut header.masked_high_cksums)?;
@@ -218,24 +448,18 @@ pub fn read_header(f: &mut BufReader<File>) -> io::Result<Header> {
     f.seek(SeekFrom::Start(0x2c))?;
     header.width = read_u8(f)? as usize;
     header.height = read_u8(f)? as usize;
-
-    let pos = f.stream_position()?;
-    println!("After reading height: pos={pos:#02x}");
+    if header.width == 0 || header.height == 0 {
+        return Err(invalid_data("puz: empty grid dimensions"))
+    }
     header.num_clues = read_u16(f)? as usize;
-
-    println!("num_clues={}", header.num_clues);
-
     header.unk_bitmask = read_u16(f)?;
     header.scrambled_tag = read_u16(f)?;
 
-    let pos = f.stream_position()?;
-    println!("After reading scrambled_tag: pos={pos:#02x}");
-
     Ok(header)
 }
 
-pub fn read_contents(
-    f: &mut BufReader<File>,
+pub fn read_contents<R: Read>(
+    f: &mut R,
     height: usize,
     width: usize,
 ) -> io::Result<BoardContents> {
@@ -249,7 +473,7 @@ pub fn read_contents(
     Ok(BoardContents { data })
 }
 
-pub fn read_strings(f: &mut BufReader<File>, num_clues: usize) -> io::Result<PuzStrings> {
+pub fn read_strings<R: BufRead>(f: &mut R, num_clues: usize) -> io::Result<PuzStrings> {
     // let mut rest = String::new();
     // f.read_to_string(&mut rest)?;
 
@@ -283,25 +507,47 @@ pub fn read_strings(f: &mut BufReader<File>, num_clues: usize) -> io::Result<Puz
 }
 
 
+pub fn read_extensions<R: Read>(f: &mut R) -> io::Result<Vec<Extension>> {
+    let mut extensions = Vec::new();
 
-fn read_u16(f: &mut BufReader<File>) -> io::Result<u16> {
+    loop {
+        let mut title_buf = [0u8; 4];
+        match f.read_exact(&mut title_buf) {
+            Ok(()) => {},
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err)
+        }
+
+        let title = latin1_to_str(&title_buf);
+        let len = read_u16(f)? as usize;
+        let _cksum = read_u16(f)?;
+        let data = read_len(f, len)?;
+        let _terminator = read_u8(f)?;
+
+        extensions.push(Extension { title, data })
+    }
+
+    Ok(extensions)
+}
+
+fn read_u16<R: Read>(f: &mut R) -> io::Result<u16> {
     let mut short_buf = [0; 2];
     f.read_exact(&mut short_buf)?;
     Ok(u16::from_le_bytes(short_buf))
 }
 
-fn read_u8(f: &mut BufReader<File>) -> io::Result<u8> {
+fn read_u8<R: Read>(f: &mut R) -> io::Result<u8> {
     let mut one_byte = [0; 1];
     f.read_exact(&mut one_byte)?;
     Ok(one_byte[0])
 }
 
-fn read_latin1_string(f: &mut BufReader<File>, len: usize) -> io::Result<String> {
+fn read_latin1_string<R: Read>(f: &mut R, len: usize) -> io::Result<String> {
     let bytes = read_len(f, len)?;
     Ok(latin1_to_str(&bytes))
 }
 
-fn read_len(f: &mut BufReader<File>, len: usize) -> io::Result<Vec<u8>> {
+fn read_len<R: Read>(f: &mut R, len: usize) -> io::Result<Vec<u8>> {
     let mut buf = vec![0u8; len];
     f.read_exact(&mut buf)?;
     Ok(buf)
@@ -311,3 +557,42 @@ fn latin1_to_str(bytes: &[u8]) -> String {
     bytes.iter().map(|&by| by as char).collect()
 }
 
+/// YYYY-MM-DD of the day `days` after 1970-01-01, see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(crate) fn format_date(days: i64) -> String {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip_keeps_markers() {
+        let markers = BTreeMap::from([
+            (Pos::new(0, 1), vec![Marker::Bar(Side::Right)]),
+            (Pos::new(1, 0), vec![Marker::Triangle(Side::Top), Marker::Circle]),
+        ]);
+        let puz = ImportedPuz::from_grid(
+            vec![vec!['A', 'B'], vec!['C', 'D']],
+            vec![vec!['-', '-'], vec!['-', '-']],
+            PuzStrings::default(),
+            &BTreeMap::new(),
+            Vec::new(),
+        ).with_markers(markers);
+
+        let json = serde_json::to_string(&puz).unwrap();
+        let read_back: ImportedPuz = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back.markers, puz.markers);
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use toml::{Table, Value};
+
+use crate::api_types::Direction;
+use crate::msg::Msg;
+use crate::util::invalid_data;
+
+/// Something a key chord can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    ToggleDirection,
+    Backspace,
+    ClearCell,
+    Advance,
+    ClearWord,
+    NextClue,
+    PrevClue,
+    Undo,
+    Redo,
+    Save,
+    Open,
+    CheckSquare,
+    CheckWord,
+    CheckPuzzle,
+    RevealSquare,
+    RevealWord,
+    RevealPuzzle,
+    TogglePencil,
+    ToggleAutocheck,
+    InkWord,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+}
+
+/// Names used for actions in the config file
+const ACTION_NAMES: [(Action, &str); 27] = [
+    (Action::MoveLeft, "move_left"),
+    (Action::MoveRight, "move_right"),
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::ToggleDirection, "toggle_direction"),
+    (Action::Backspace, "backspace"),
+    (Action::ClearCell, "clear_cell"),
+    (Action::Advance, "advance"),
+    (Action::ClearWord, "clear_word"),
+    (Action::NextClue, "next_clue"),
+    (Action::PrevClue, "prev_clue"),
+    (Action::Undo, "undo"),
+    (Action::Redo, "redo"),
+    (Action::Save, "save"),
+    (Action::Open, "open"),
+    (Action::CheckSquare, "check_square"),
+    (Action::CheckWord, "check_word"),
+    (Action::CheckPuzzle, "check_puzzle"),
+    (Action::RevealSquare, "reveal_square"),
+    (Action::RevealWord, "reveal_word"),
+    (Action::RevealPuzzle, "reveal_puzzle"),
+    (Action::TogglePencil, "toggle_pencil"),
+    (Action::ToggleAutocheck, "toggle_autocheck"),
+    (Action::InkWord, "ink_word"),
+    (Action::ZoomIn, "zoom_in"),
+    (Action::ZoomOut, "zoom_out"),
+    (Action::ZoomReset, "zoom_reset"),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES.iter().find(|(_, action_name)| *action_name == name).map(|(action, _)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTION_NAMES.iter().find(|(action, _)| action == self).map(|(_, name)| *name).unwrap_or("")
+    }
+
+    pub fn msg(&self) -> Msg {
+        match self {
+            Action::MoveLeft => Msg::MoveCursor(Direction::Left),
+            Action::MoveRight => Msg::MoveCursor(Direction::Right),
+            Action::MoveUp => Msg::MoveCursor(Direction::Up),
+            Action::MoveDown => Msg::MoveCursor(Direction::Down),
+            Action::ToggleDirection => Msg::ToggleTypingDir,
+            Action::Backspace => Msg::Backspace,
+            Action::ClearCell => Msg::ClearCell,
+            Action::Advance => Msg::Advance,
+            Action::ClearWord => Msg::ClearWord,
+            Action::NextClue => Msg::NextClue,
+            Action::PrevClue => Msg::PrevClue,
+            Action::Undo => Msg::Undo,
+            Action::Redo => Msg::Redo,
+            Action::Save => Msg::Save,
+            Action::Open => Msg::Open,
+            Action::CheckSquare => Msg::CheckSquare,
+            Action::CheckWord => Msg::CheckWord,
+            Action::CheckPuzzle => Msg::CheckPuzzle,
+            Action::RevealSquare => Msg::RevealSquare,
+            Action::RevealWord => Msg::RevealWord,
+            Action::RevealPuzzle => Msg::RevealPuzzle,
+            Action::TogglePencil => Msg::TogglePencil,
+            Action::ToggleAutocheck => Msg::ToggleAutocheck,
+            Action::InkWord => Msg::InkWord,
+            Action::ZoomIn => Msg::ZoomIn,
+            Action::ZoomOut => Msg::ZoomOut,
+            Action::ZoomReset => Msg::ZoomReset,
+        }
+    }
+}
+
+/// Named keys that can be bound, with the name used for them in the config file
+const NAMED_KEYS: [(Named, &str); 13] = [
+    (Named::ArrowLeft, "Left"),
+    (Named::ArrowRight, "Right"),
+    (Named::ArrowUp, "Up"),
+    (Named::ArrowDown, "Down"),
+    (Named::Backspace, "Backspace"),
+    (Named::Delete, "Delete"),
+    (Named::Space, "Space"),
+    (Named::Tab, "Tab"),
+    (Named::Enter, "Enter"),
+    (Named::Escape, "Escape"),
+    (Named::Home, "Home"),
+    (Named::End, "End"),
+    (Named::Insert, "Insert"),
+];
+
+/// A key with modifiers, written like "Ctrl+Shift+Z" in the config file.
+/// `ctrl` is the platform's command modifier, i.e. Cmd on macOS.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyChord {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    /// A name from `NAMED_KEYS` or a lowercase character
+    key: String,
+}
+
+impl KeyChord {
+    pub fn parse(st: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = st.split('+').map(|part| part.trim()).collect();
+        let key_part = parts.pop().filter(|part| !part.is_empty())?;
+
+        let mut chord = KeyChord { ctrl: false, alt: false, shift: false, key: String::new() };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => chord.ctrl = true,
+                "alt" | "option" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return None
+            }
+        }
+
+        let named = NAMED_KEYS.iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(key_part) || format!("Arrow{name}").eq_ignore_ascii_case(key_part));
+        chord.key = match named {
+            Some((_, name)) => name.to_string(),
+            None if key_part.chars().count() == 1 => key_part.to_lowercase(),
+            None => return None
+        };
+
+        Some(chord)
+    }
+
+    /// The chord for a key event, None for keys that can't be bound
+    pub fn from_event(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        let key = match key {
+            Key::Named(named) => NAMED_KEYS.iter().find(|(key, _)| key == named)?.1.to_string(),
+            Key::Character(st) => st.to_lowercase(),
+            Key::Unidentified => return None
+        };
+
+        Some(KeyChord { ctrl: modifiers.command(), alt: modifiers.alt(), shift: modifiers.shift(), key })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+")] {
+            if held {
+                f.write_str(name)?
+            }
+        }
+        if self.key.chars().count() == 1 {
+            f.write_str(&self.key.to_uppercase())
+        } else {
+            f.write_str(&self.key)
+        }
+    }
+}
+
+/// Shipped key bindings, mimicking popular solving apps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    #[default]
+    AcrossLite,
+    Nyt,
+    Vim,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "across_lite" | "acrosslite" => Some(Preset::AcrossLite),
+            "nyt" => Some(Preset::Nyt),
+            "vim" => Some(Preset::Vim),
+            _ => None
+        }
+    }
+
+    fn bindings(&self) -> Vec<(&'static str, Action)> {
+        let mut ret = vec![
+            ("Left", Action::MoveLeft),
+            ("Right", Action::MoveRight),
+            ("Up", Action::MoveUp),
+            ("Down", Action::MoveDown),
+            ("Backspace", Action::Backspace),
+            ("Delete", Action::ClearCell),
+            ("Tab", Action::NextClue),
+            ("Shift+Tab", Action::PrevClue),
+            ("Ctrl+Z", Action::Undo),
+            ("Ctrl+Shift+Z", Action::Redo),
+            ("Ctrl+S", Action::Save),
+            ("Ctrl+O", Action::Open),
+            ("Ctrl+=", Action::ZoomIn),
+            ("Ctrl+-", Action::ZoomOut),
+            ("Ctrl+0", Action::ZoomReset),
+        ];
+
+        match self {
+            Preset::AcrossLite => ret.extend([
+                ("Shift+Right", Action::ToggleDirection),
+                ("Ctrl+Right", Action::ToggleDirection),
+                ("Shift+Down", Action::ToggleDirection),
+                ("Ctrl+Down", Action::ToggleDirection),
+                ("Ctrl+Backspace", Action::ClearWord),
+                ("Space", Action::Advance),
+            ]),
+            Preset::Nyt => ret.extend([
+                ("Space", Action::ToggleDirection),
+                ("Enter", Action::NextClue),
+                ("Shift+Enter", Action::PrevClue),
+                ("Ctrl+Backspace", Action::ClearWord),
+            ]),
+            Preset::Vim => ret.extend([
+                ("Alt+H", Action::MoveLeft),
+                ("Alt+J", Action::MoveDown),
+                ("Alt+K", Action::MoveUp),
+                ("Alt+L", Action::MoveRight),
+                ("Alt+T", Action::ToggleDirection),
+                ("Alt+X", Action::ClearCell),
+                ("Ctrl+W", Action::ClearWord),
+                ("Alt+W", Action::NextClue),
+                ("Alt+B", Action::PrevClue),
+                ("Alt+U", Action::Undo),
+                ("Ctrl+R", Action::Redo),
+                ("Space", Action::Advance),
+            ]),
+        }
+
+        ret
+    }
+}
+
+/// Maps key chords to actions. Characters without a binding type letters.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: BTreeMap<KeyChord, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset(Preset::default())
+    }
+}
+
+impl Keymap {
+    pub fn preset(preset: Preset) -> Self {
+        let bindings = preset.bindings().into_iter()
+            .filter_map(|(chord, action)| Some((KeyChord::parse(chord)?, action)))
+            .collect();
+        Keymap { bindings }
+    }
+
+    /// Reads the `[keymap]` config table: a `preset` name and a `bindings` table from action
+    /// names to lists of chords, replacing the preset's chords for those actions.
+    /// Returns the keymap along with the conflicting bindings found.
+    pub fn from_toml(table: &Table) -> io::Result<(Self, Vec<String>)> {
+        let preset = match table.get("preset") {
+            Some(Value::String(name)) => Preset::from_name(name)
+                .ok_or_else(|| invalid_data(format!("keymap: unknown preset {name:?}")))?,
+            Some(_) => return Err(invalid_data("keymap: preset must be a string")),
+            None => Preset::default()
+        };
+        let mut keymap = Keymap::preset(preset);
+
+        let Some(bindings) = table.get("bindings") else {
+            return Ok((keymap, Vec::new()))
+        };
+        let bindings = bindings.as_table()
+            .ok_or_else(|| invalid_data("keymap: bindings must be a table"))?;
+
+        let mut user_bindings: Vec<(KeyChord, Action)> = Vec::new();
+        for (action_name, chords) in bindings {
+            let action = Action::from_name(action_name)
+                .ok_or_else(|| invalid_data(format!("keymap: unknown action {action_name:?}")))?;
+            let chords = match chords {
+                Value::String(chord) => vec![chord.as_str()],
+                Value::Array(chords) => chords.iter().filter_map(|chord| chord.as_str()).collect(),
+                _ => return Err(invalid_data(format!("keymap: bad chords for {action_name}")))
+            };
+
+            keymap.bindings.retain(|_, bound| *bound != action);
+            for chord in chords {
+                let chord = KeyChord::parse(chord)
+                    .ok_or_else(|| invalid_data(format!("keymap: bad key chord {chord:?}")))?;
+                user_bindings.push((chord, action))
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        for (chord, action) in user_bindings.iter() {
+            let clashing = user_bindings.iter()
+                .find(|(other_chord, other_action)| other_chord == chord && other_action != action);
+            match (clashing, keymap.bindings.get(chord)) {
+                (Some((_, other)), _) if action < other => conflicts.push(format!(
+                    "{chord} is bound to both {} and {}", action.name(), other.name())),
+                (None, Some(preset_action)) if preset_action != action => conflicts.push(format!(
+                    "{chord} is bound to {} by the preset, now to {}", preset_action.name(), action.name())),
+                _ => ()
+            }
+        }
+
+        keymap.bindings.extend(user_bindings);
+        Ok((keymap, conflicts))
+    }
+
+    pub fn action_for(&self, chord: &KeyChord) -> Option<Action> {
+        self.bindings.get(chord).cloned()
+    }
+
+    /// The message for a key release: the bound action, or typing the letter
+    pub fn msg_for(&self, key: &Key, modifiers: Modifiers) -> Option<Msg> {
+        let chord = KeyChord::from_event(key, modifiers)?;
+        if let Some(action) = self.action_for(&chord) {
+            return Some(action.msg())
+        }
+
+        match key {
+            Key::Character(st) if !modifiers.command() && !modifiers.alt() => {
+                st.chars().last().map(|ch| Msg::TypeLetter(ch.to_ascii_uppercase()))
+            },
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(config: &str) -> io::Result<(Keymap, Vec<String>)> {
+        Keymap::from_toml(&config.parse::<Table>().unwrap())
+    }
+
+    fn chord(st: &str) -> KeyChord {
+        KeyChord::parse(st).unwrap()
+    }
+
+    #[test]
+    fn parses_chords_with_modifiers() {
+        let redo = chord("Ctrl+Shift+Z");
+        assert_eq!(Some(redo.clone()), KeyChord::from_event(&Key::Character("z".into()), Modifiers::COMMAND | Modifiers::SHIFT));
+        assert_eq!(redo, chord("shift + control + z"));
+        assert_eq!(redo.to_string(), "Ctrl+Shift+Z");
+
+        assert_eq!(KeyChord::parse("Hyper+Z"), None);
+        assert_eq!(KeyChord::parse("Ctrl+"), None);
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        assert_eq!(chord("ArrowLeft"), chord("left"));
+        assert_eq!(Some(chord("Shift+Tab")), KeyChord::from_event(&Key::Named(Named::Tab), Modifiers::SHIFT));
+        assert_eq!(chord("Ctrl+Backspace").to_string(), "Ctrl+Backspace");
+        assert_eq!(KeyChord::parse("PageDown"), None);
+    }
+
+    #[test]
+    fn bindings_replace_the_preset_chords_of_their_action() {
+        let (keymap, conflicts) = keymap(r#"
+            preset = "nyt"
+            [bindings]
+            undo = ["Alt+U", "Ctrl+Y"]
+        "#).unwrap();
+
+        assert!(conflicts.is_empty());
+        assert_eq!(keymap.action_for(&chord("Alt+U")), Some(Action::Undo));
+        assert_eq!(keymap.action_for(&chord("Ctrl+Y")), Some(Action::Undo));
+        assert_eq!(keymap.action_for(&chord("Ctrl+Z")), None);
+        // the rest of the preset stays
+        assert_eq!(keymap.action_for(&chord("Space")), Some(Action::ToggleDirection));
+    }
+
+    #[test]
+    fn reports_conflicting_bindings() {
+        let (keymap, conflicts) = keymap(r#"
+            [bindings]
+            undo = "Ctrl+S"
+            ink_word = "Alt+I"
+            reveal_word = "Alt+I"
+        "#).unwrap();
+
+        assert_eq!(conflicts, vec![
+            "Alt+I is bound to both reveal_word and ink_word",
+            "Ctrl+S is bound to save by the preset, now to undo",
+        ]);
+        assert_eq!(keymap.action_for(&chord("Ctrl+S")), Some(Action::Undo));
+    }
+
+    #[test]
+    fn rejects_unknown_actions_and_presets() {
+        let err = keymap("[bindings]\nfly = \"Ctrl+F\"").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(keymap("preset = \"emacs\"").is_err());
+        assert!(keymap("[bindings]\nundo = \"Ctrl+Nope\"").is_err());
+    }
+
+    #[test]
+    fn unbound_characters_type_letters() {
+        let keymap = Keymap::default();
+        assert!(matches!(keymap.msg_for(&Key::Character("q".into()), Modifiers::empty()), Some(Msg::TypeLetter('Q'))));
+        assert!(matches!(keymap.msg_for(&Key::Character("z".into()), Modifiers::COMMAND), Some(Msg::Undo)));
+        assert!(keymap.msg_for(&Key::Character("q".into()), Modifiers::COMMAND).is_none());
+    }
+}
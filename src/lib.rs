@@ -0,0 +1,20 @@
+pub mod state;
+pub mod history;
+pub mod app;
+pub mod view;
+pub mod api_types;
+pub mod msg;
+pub mod import_puz;
+pub mod import_ipuz;
+pub mod import_guardian;
+pub mod loader;
+pub mod export_latex;
+pub mod export_puz;
+pub mod session;
+pub mod config;
+pub mod keymap;
+pub mod palette;
+pub mod library;
+mod util;
+#[cfg(feature = "serde")]
+pub mod serde_util;
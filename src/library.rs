@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::import_puz::{format_date, ImportedPuz};
+use crate::loader::{self, PuzFormat};
+use crate::session::{puzzle_hash, Session};
+use crate::state::{Board, Completion};
+
+/// A puzzle file found while scanning the library directories
+#[derive(Debug, Clone)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    /// Name of the puzzle inside `path` when that is a zip archive
+    pub zip_entry: Option<String>,
+    pub title: String,
+    pub author: String,
+    /// Date of the puzzle, or the modification date of the file, as YYYY-MM-DD
+    pub date: String,
+    pub width: usize,
+    pub height: usize,
+    /// Share of open cells filled in, 0.0 to 1.0
+    pub progress: f32,
+    pub solved: bool,
+}
+
+impl LibraryEntry {
+    pub fn size(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+
+    /// Case insensitive match of `filter` against title, author, date and size
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        [&self.title, &self.author, &self.date, &self.size()].iter()
+            .any(|field| field.to_lowercase().contains(&filter))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LibrarySort {
+    #[default]
+    Title,
+    Author,
+    Date,
+    Size,
+    Progress,
+}
+
+pub fn sort_entries(entries: &mut [LibraryEntry], sort: LibrarySort) {
+    match sort {
+        LibrarySort::Title => entries.sort_by(|a, b| a.title.cmp(&b.title)),
+        LibrarySort::Author => entries.sort_by(|a, b| a.author.cmp(&b.author)),
+        // newest first
+        LibrarySort::Date => entries.sort_by(|a, b| b.date.cmp(&a.date)),
+        LibrarySort::Size => entries.sort_by_key(|entry| entry.width * entry.height),
+        LibrarySort::Progress => entries.sort_by(|a, b| b.progress.total_cmp(&a.progress)),
+    }
+}
+
+/// Indexes every supported puzzle file under `dirs`, and the puzzles inside zip archives,
+/// with their progress taken from the autosaved session. Files that fail to import are skipped.
+pub fn scan_library(dirs: &[PathBuf]) -> Vec<LibraryEntry> {
+    let mut paths = Vec::new();
+    for dir in dirs {
+        collect_puzzle_files(dir, &mut paths)
+    }
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let indexed = if loader::is_zip(&path) {
+            index_archive(&path)
+        } else {
+            loader::load_puzzle(&path)
+                .and_then(|imported_puz| index_puzzle(&path, None, &imported_puz))
+                .map(|entry| vec![entry])
+        };
+        match indexed {
+            Ok(indexed) => entries.extend(indexed),
+            Err(err) => eprintln!("Skipping {path:?}: {err}")
+        }
+    }
+    entries
+}
+
+fn collect_puzzle_files(dir: &Path, paths: &mut Vec<PathBuf>) {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) => {
+            eprintln!("Could not scan {dir:?}: {err}");
+            return
+        }
+    };
+
+    for dir_entry in read_dir.flatten() {
+        let path = dir_entry.path();
+        if path.is_dir() {
+            collect_puzzle_files(&path, paths)
+        } else if PuzFormat::from_path(&path).is_some() || loader::is_zip(&path) {
+            paths.push(path)
+        }
+    }
+}
+
+fn index_archive(path: &Path) -> std::io::Result<Vec<LibraryEntry>> {
+    loader::import_zip(path)?.into_iter()
+        .map(|(name, imported_puz)| index_puzzle(path, Some(name), &imported_puz))
+        .collect()
+}
+
+fn index_puzzle(path: &Path, zip_entry: Option<String>, imported_puz: &ImportedPuz) -> std::io::Result<LibraryEntry> {
+    let mut board = Board::from_puzzle(imported_puz.title().to_string(), imported_puz);
+    // a session that cannot be read only costs the progress column, not the puzzle
+    if let Some(session) = Session::load(&puzzle_hash(imported_puz)).ok().flatten() {
+        session.apply_to(&mut board)
+    }
+
+    let stats = board.solve_stats();
+    let progress = if stats.open_cells == 0 {
+        0.
+    } else {
+        stats.filled as f32 / stats.open_cells as f32
+    };
+
+    // the puzzle's own date when it has one, else the day the file was last changed
+    let date = if imported_puz.date().is_empty() {
+        fs::metadata(path)?.modified().ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| format_date((since_epoch.as_secs() / 86400) as i64))
+            .unwrap_or_default()
+    } else {
+        imported_puz.date().to_string()
+    };
+
+    Ok(LibraryEntry {
+        path: path.to_path_buf(),
+        zip_entry,
+        title: imported_puz.title().to_string(),
+        author: imported_puz.author().to_string(),
+        date,
+        width: imported_puz.width(),
+        height: imported_puz.height(),
+        progress,
+        solved: board.completion == Completion::Solved,
+    })
+}
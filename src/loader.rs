@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::import_guardian::import_guardian;
+use crate::import_ipuz::import_ipuz;
+use crate::import_puz::{import_puzzle, ImportedPuz};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzFormat {
+    Puz,
+    Ipuz,
+    /// The Guardian's crossword JSON schema
+    Guardian,
+}
+
+impl PuzFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "puz" => Some(Self::Puz),
+            "ipuz" => Some(Self::Ipuz),
+            "json" => Some(Self::Guardian),
+            _ => None
+        }
+    }
+}
+
+/// A puzzle found inside a zip archive
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub title: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Loads a puzzle file, picking the importer from the file extension
+pub fn load_puzzle(path: &Path) -> io::Result<ImportedPuz> {
+    match PuzFormat::from_path(path) {
+        Some(PuzFormat::Puz) => import_puzzle(&mut BufReader::new(File::open(path)?)),
+        Some(PuzFormat::Ipuz) => import_ipuz(&mut BufReader::new(File::open(path)?)),
+        Some(PuzFormat::Guardian) => import_guardian(&mut BufReader::new(File::open(path)?)),
+        None => Err(unsupported(path))
+    }
+}
+
+/// Imports a puzzle already held in memory
+pub fn import_bytes(format: PuzFormat, bytes: &[u8]) -> io::Result<ImportedPuz> {
+    let mut cursor = Cursor::new(bytes);
+    match format {
+        PuzFormat::Puz => import_puzzle(&mut cursor),
+        PuzFormat::Ipuz => import_ipuz(&mut cursor),
+        PuzFormat::Guardian => import_guardian(&mut cursor),
+    }
+}
+
+/// Lists the puzzles inside a zip archive. Entries that fail to import are skipped.
+pub fn list_zip(path: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let entries = import_zip(path)?.into_iter()
+        .map(|(name, puz)| ArchiveEntry {
+            title: puz.title().to_string(),
+            width: puz.width(),
+            height: puz.height(),
+            name,
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Imports every puzzle inside a zip archive, along with its entry name. Entries that
+/// cannot be read or imported are skipped.
+pub fn import_zip(path: &Path) -> io::Result<Vec<(String, ImportedPuz)>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut ret = Vec::new();
+
+    for idx in 0..archive.len() {
+        let mut entry = match archive.by_index(idx) {
+            Ok(entry) => entry,
+            Err(err) => {
+                eprintln!("Skipping entry {idx} in {path:?}: {err}");
+                continue
+            }
+        };
+        let name = entry.name().to_string();
+        let Some(format) = zip_entry_format(&name) else {
+            continue
+        };
+
+        let mut bytes = Vec::new();
+        let imported = entry.read_to_end(&mut bytes).and_then(|_| import_bytes(format, &bytes));
+        match imported {
+            Ok(puz) => ret.push((name, puz)),
+            Err(err) => eprintln!("Skipping {name} in {path:?}: {err}")
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Imports one puzzle out of a zip archive, without extracting it to disk
+pub fn load_from_zip(path: &Path, entry_name: &str) -> io::Result<ImportedPuz> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut entry = archive.by_name(entry_name)?;
+    let format = zip_entry_format(entry_name).ok_or_else(|| unsupported(Path::new(entry_name)))?;
+
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    import_bytes(format, &bytes)
+}
+
+pub fn is_zip(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+fn zip_entry_format(name: &str) -> Option<PuzFormat> {
+    let path = Path::new(name);
+    // Skip resource forks added by macOS archivers
+    let is_resource_fork = name.starts_with("__MACOSX/")
+        || path.file_name().and_then(|f| f.to_str()).is_some_and(|f| f.starts_with("._"));
+
+    if is_resource_fork {
+        None
+    } else {
+        PuzFormat::from_path(path)
+    }
+}
+
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("unsupported puzzle file: {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+    use crate::export_puz::write_puzzle;
+    use crate::import_puz::PuzStrings;
+
+    const IPUZ: &str = r#"{"title": "From ipuz", "dimensions": {"width": 2, "height": 1},
+        "puzzle": [[1, 0]], "solution": [["H", "I"]]}"#;
+
+    /// An archive holding a .puz, an .ipuz, a broken .ipuz, a resource fork and a text file
+    fn write_archive(name: &str) -> PathBuf {
+        let mut puz_bytes = Vec::new();
+        let strings = PuzStrings::new("From puz".to_string(), String::new(), String::new(), String::new());
+        let puz = ImportedPuz::from_grid(vec![vec!['A', 'B']], vec![vec!['-', '-']], strings, &BTreeMap::new(), Vec::new());
+        write_puzzle(&puz, &mut puz_bytes).unwrap();
+
+        let path = std::env::temp_dir().join(format!("cross-iced-{}-{name}.zip", std::process::id()));
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (entry_name, bytes) in [
+            ("week/mon.puz", &puz_bytes[..]),
+            ("tue.ipuz", IPUZ.as_bytes()),
+            ("wed.ipuz", b"not json".as_slice()),
+            ("__MACOSX/week/._mon.puz", &puz_bytes[..]),
+            ("readme.txt", b"hello".as_slice()),
+        ] {
+            zip.start_file(entry_name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn lists_the_puzzles_that_import() {
+        let path = write_archive("list");
+        let entries = list_zip(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let listed: Vec<(&str, &str, usize, usize)> = entries.iter()
+            .map(|entry| (entry.name.as_str(), entry.title.as_str(), entry.width, entry.height))
+            .collect();
+        assert_eq!(listed, vec![("week/mon.puz", "From puz", 2, 1), ("tue.ipuz", "From ipuz", 2, 1)]);
+    }
+
+    #[test]
+    fn loads_a_single_entry() {
+        let path = write_archive("load");
+        let puz = load_from_zip(&path, "tue.ipuz");
+        let missing = load_from_zip(&path, "thu.ipuz");
+        let broken = load_from_zip(&path, "wed.ipuz");
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(puz.unwrap().title(), "From ipuz");
+        assert!(missing.is_err());
+        assert_eq!(broken.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn picks_the_importer_from_the_extension() {
+        assert_eq!(PuzFormat::from_path(Path::new("a.PUZ")), Some(PuzFormat::Puz));
+        assert_eq!(PuzFormat::from_path(Path::new("a.ipuz")), Some(PuzFormat::Ipuz));
+        assert_eq!(PuzFormat::from_path(Path::new("a.json")), Some(PuzFormat::Guardian));
+        assert_eq!(PuzFormat::from_path(Path::new("a.txt")), None);
+        assert!(is_zip(Path::new("pack.Zip")));
+    }
+}
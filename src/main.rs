@@ -1,19 +1,64 @@
+use std::path::{Path, PathBuf};
+
 use iced::Application;
 use iced::{self, Settings};
 
-pub mod state;
-pub mod app;
-pub mod view;
-pub mod api_types;
-pub mod import_puz;
+use cross_iced::app::App;
+use cross_iced::export_latex::{self, LatexOptions};
+use cross_iced::loader;
 
 
-use state::Board;
+fn main() -> iced::Result {
+    let args: Vec<String> = std::env::args().collect();
 
+    if args.get(1).map(|arg| arg.as_str()) == Some("export-latex") {
+        if let Err(err) = export_latex_cmd(&args[2..]) {
+            eprintln!("export-latex: {err}");
+            std::process::exit(1)
+        }
+        return Ok(())
+    }
 
-fn main() -> iced::Result {
+    if args.get(1).map(|arg| arg.as_str()) == Some("list-zip") {
+        if let Err(err) = list_zip_cmd(&args[2..]) {
+            eprintln!("list-zip: {err}");
+            std::process::exit(1)
+        }
+        return Ok(())
+    }
+
+    // `cross-iced [puzzle]`
+    App::run(Settings::with_flags(args.get(1).map(PathBuf::from)))
+}
+
+/// `cross-iced export-latex <puzzle> <out.tex> [--solved] [--no-clues]`
+fn export_latex_cmd(args: &[String]) -> Result<(), String> {
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [puz_path, out_path] = paths[..] else {
+        return Err("usage: cross-iced export-latex <puzzle> <out.tex> [--solved] [--no-clues]".to_string())
+    };
+
+    let opts = LatexOptions {
+        solved: args.iter().any(|arg| arg == "--solved"),
+        with_clues: !args.iter().any(|arg| arg == "--no-clues"),
+    };
+
+    let imported_puz = loader::load_puzzle(Path::new(puz_path)).map_err(|err| format!("{puz_path}: {err}"))?;
+
+    std::fs::write(out_path, export_latex::export_latex(&imported_puz, &opts))
+        .map_err(|err| format!("{out_path}: {err}"))
+}
+
+/// `cross-iced list-zip <archive.zip>`
+fn list_zip_cmd(args: &[String]) -> Result<(), String> {
+    let [zip_path] = args else {
+        return Err("usage: cross-iced list-zip <archive.zip>".to_string())
+    };
 
-    // let header = read_header(&mut f).unwrap();
+    let entries = loader::list_zip(Path::new(zip_path)).map_err(|err| format!("{zip_path}: {err}"))?;
+    for entry in entries {
+        println!("{name}\t{w}x{h}\t{title}", name=entry.name, w=entry.width, h=entry.height, title=entry.title);
+    }
 
-    Board::run(Settings::default())
+    Ok(())
 }
@@ -0,0 +1,63 @@
+//! Messages of the iced frontend, kept out of `api_types` so that the board core
+//! doesn't depend on iced
+
+use std::path::PathBuf;
+
+use iced::keyboard::{Key, Modifiers};
+use iced::Size;
+
+use crate::api_types::{Direction, Pos, TypingDir};
+use crate::library::{LibraryEntry, LibrarySort};
+
+#[derive(Debug, Clone)]
+pub enum Msg {
+    MoveCursor(Direction),
+    TypeLetter(char),
+    ClearCell,
+    Backspace,
+    Advance,
+    ClearWord,
+    ToggleTypingDir,
+    Undo,
+    Redo,
+    ToggleAutocheck,
+    CheckSquare,
+    CheckWord,
+    CheckPuzzle,
+    RevealSquare,
+    RevealWord,
+    RevealPuzzle,
+    Save,
+    TogglePencil,
+    InkWord,
+    NextClue,
+    PrevClue,
+    ToggleSkipFullEntries,
+    ToggleSkipFilledCells,
+    ToggleJumpToNextEntry,
+    ClickCell(Pos),
+    SelectEntry(Pos, TypingDir),
+    StartFresh,
+    /// Ask for a puzzle file with the native file picker
+    Open,
+    /// Picked puzzle file, None when the picker was cancelled
+    OpenPath(Option<PathBuf>),
+    /// Open the puzzle stored under the given entry name of a zip archive
+    OpenZipEntry(PathBuf, String),
+    /// Scan the library directories and list their puzzles
+    ShowLibrary,
+    LibraryIndexed(Vec<LibraryEntry>),
+    SortLibrary(LibrarySort),
+    FilterLibrary(String),
+    ToggleHideSolved,
+    /// Resolved to another message through the keymap
+    KeyReleased(Key, Modifiers),
+    /// Switch to the palette with this name
+    SelectPalette(String),
+    /// Hide the config problems shown above the screen
+    DismissNotices,
+    WindowResized(Size),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+}
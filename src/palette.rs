@@ -0,0 +1,213 @@
+use std::io;
+
+use iced::Color;
+use toml::{Table, Value};
+
+use crate::util::invalid_data;
+
+/// Colours used to draw the grid and clues
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Use iced's dark theme for the widgets around the grid
+    pub dark: bool,
+    pub background: Color,
+    pub text: Color,
+    pub grid_lines: Color,
+    /// The cursor cell, and the active clue in the clue lists
+    pub cursor: Color,
+    /// Other cells of the current word
+    pub word: Color,
+    /// The crossing clue in the clue lists
+    pub crossing: Color,
+    /// Entries the current clue refers to
+    pub reference: Color,
+    pub wrong: Color,
+    pub revealed: Color,
+    pub pencil: Color,
+    pub black: Color,
+    /// The crossing clue shown under the current one
+    pub crossing_text: Color,
+    /// The congratulations once the puzzle is solved
+    pub solved: Color,
+}
+
+impl Palette {
+    pub const LIGHT: Palette = Palette {
+        dark: false,
+        background: Color::WHITE,
+        text: Color::BLACK,
+        grid_lines: Color::BLACK,
+        cursor: rgb8(150, 250, 255),
+        word: rgb8(200, 250, 255),
+        crossing: rgb8(220, 220, 235),
+        reference: rgb8(255, 235, 166),
+        wrong: rgb8(220, 0, 0),
+        revealed: rgb8(30, 80, 220),
+        pencil: rgb8(140, 140, 140),
+        black: rgb8(10, 10, 10),
+        crossing_text: rgb8(110, 110, 110),
+        solved: rgb8(0, 150, 60),
+    };
+
+    pub const DARK: Palette = Palette {
+        dark: true,
+        background: rgb8(40, 40, 46),
+        text: rgb8(230, 230, 230),
+        grid_lines: rgb8(120, 120, 130),
+        cursor: rgb8(30, 110, 140),
+        word: rgb8(35, 70, 90),
+        crossing: rgb8(60, 60, 75),
+        reference: rgb8(110, 90, 30),
+        wrong: rgb8(255, 90, 90),
+        revealed: rgb8(120, 160, 255),
+        pencil: rgb8(150, 150, 150),
+        black: Color::BLACK,
+        crossing_text: rgb8(170, 170, 170),
+        solved: rgb8(90, 210, 120),
+    };
+
+    pub const HIGH_CONTRAST: Palette = Palette {
+        dark: false,
+        background: Color::WHITE,
+        text: Color::BLACK,
+        grid_lines: Color::BLACK,
+        cursor: rgb8(255, 210, 0),
+        word: rgb8(140, 210, 255),
+        crossing: rgb8(200, 200, 200),
+        reference: rgb8(255, 140, 255),
+        wrong: rgb8(200, 0, 0),
+        revealed: rgb8(0, 0, 200),
+        pencil: rgb8(80, 80, 80),
+        black: Color::BLACK,
+        crossing_text: rgb8(50, 50, 50),
+        solved: rgb8(0, 110, 0),
+    };
+
+    /// The shipped palettes, by name
+    pub fn presets() -> Vec<(String, Palette)> {
+        vec![
+            ("light".to_string(), Palette::LIGHT),
+            ("dark".to_string(), Palette::DARK),
+            ("high-contrast".to_string(), Palette::HIGH_CONTRAST),
+        ]
+    }
+
+    /// A user palette from a `[palettes.<name>]` config table: the preset named by `base`
+    /// (light by default) with any of the colours overridden, written as "#rrggbb".
+    /// Colours that can't be read keep the base colour; they are returned as warnings.
+    pub fn from_toml(table: &Table) -> io::Result<(Self, Vec<String>)> {
+        let mut palette = match table.get("base") {
+            Some(Value::String(base)) => Palette::presets().into_iter()
+                .find(|(name, _)| name == base)
+                .map(|(_, palette)| palette)
+                .ok_or_else(|| invalid_data(format!("palette: unknown base {base:?}")))?,
+            Some(_) => return Err(invalid_data("palette: base must be a string")),
+            None => Palette::LIGHT
+        };
+
+        let mut warnings = Vec::new();
+        for (key, value) in table {
+            if key == "base" {
+                continue
+            }
+            if key == "dark" {
+                palette.dark = value.as_bool()
+                    .ok_or_else(|| invalid_data("palette: dark must be true or false"))?;
+                continue
+            }
+
+            let slot = match key.as_str() {
+                "background" => &mut palette.background,
+                "text" => &mut palette.text,
+                "grid_lines" => &mut palette.grid_lines,
+                "cursor" => &mut palette.cursor,
+                "word" => &mut palette.word,
+                "crossing" => &mut palette.crossing,
+                "reference" => &mut palette.reference,
+                "wrong" => &mut palette.wrong,
+                "revealed" => &mut palette.revealed,
+                "pencil" => &mut palette.pencil,
+                "black" => &mut palette.black,
+                "crossing_text" => &mut palette.crossing_text,
+                "solved" => &mut palette.solved,
+                _ => {
+                    warnings.push(format!("unknown colour {key:?}"));
+                    continue
+                }
+            };
+            match value.as_str().and_then(parse_color) {
+                Some(color) => *slot = color,
+                None => warnings.push(format!("{key} must be a colour like \"#a0b0c0\", keeping the base colour")),
+            }
+        }
+
+        Ok((palette, warnings))
+    }
+}
+
+const fn rgb8(r: u8, g: u8, b: u8) -> Color {
+    Color::from_rgb(r as f32 / 255., g as f32 / 255., b as f32 / 255.)
+}
+
+/// "#rrggbb" or "rrggbb"
+fn parse_color(st: &str) -> Option<Color> {
+    let hex = st.strip_prefix('#').unwrap_or(st);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None
+    }
+
+    let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(config: &str) -> io::Result<(Palette, Vec<String>)> {
+        Palette::from_toml(&config.parse::<Table>().unwrap())
+    }
+
+    #[test]
+    fn overrides_colours_of_the_base() {
+        let (palette, warnings) = palette(r##"
+            base = "dark"
+            cursor = "#ff8000"
+            solved = "00ff00"
+        "##).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(palette.cursor, Color::from_rgb8(255, 128, 0));
+        assert_eq!(palette.solved, Color::from_rgb8(0, 255, 0));
+        assert_eq!((palette.dark, palette.background), (true, Palette::DARK.background));
+    }
+
+    #[test]
+    fn bad_colours_keep_the_base_colour() {
+        let (palette, warnings) = palette(r##"
+            cursor = "#ff80"
+            word = "#gg0000"
+            wrong = 12
+            sparkle = "#ffffff"
+            text = "#102030"
+        "##).unwrap();
+
+        assert_eq!(warnings.len(), 4);
+        assert_eq!((palette.cursor, palette.word, palette.wrong), (Palette::LIGHT.cursor, Palette::LIGHT.word, Palette::LIGHT.wrong));
+        assert_eq!(palette.text, Color::from_rgb8(0x10, 0x20, 0x30));
+    }
+
+    #[test]
+    fn rejects_unknown_bases() {
+        assert!(palette("base = \"neon\"").is_err());
+        assert!(palette("dark = \"yes\"").is_err());
+    }
+
+    #[test]
+    fn parses_hex_colours() {
+        assert_eq!(parse_color("#000000"), Some(Color::BLACK));
+        assert_eq!(parse_color("FFFFFF"), Some(Color::WHITE));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("#ffffé"), None);
+    }
+}
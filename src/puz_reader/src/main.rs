@@ -1,15 +1,29 @@
-mod import_puz;
-use crate::import_puz::import_puzzle;
-
 use std::fs::File;
 use std::io::BufReader;
 
+use cross_iced::import_puz::import_puzzle;
+
 
+/// `puz_reader <file.puz>`: dumps what the importer reads from a .puz file
 fn main() {
-    let path = "/home/teo/Downloads/wsj240702.puz";
-    let mut f = BufReader::new(File::open(path).unwrap());
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: puz_reader <file.puz>");
+        std::process::exit(2)
+    };
+
+    let mut f = match File::open(&path) {
+        Ok(f) => BufReader::new(f),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            std::process::exit(1)
+        }
+    };
 
-    // let header = read_header(&mut f).unwrap();
-    let imported_puz = import_puzzle(&mut f).unwrap();
-    println!("Imported_puz: {imported_puz:?}")
+    match import_puzzle(&mut f) {
+        Ok(imported_puz) => println!("Imported_puz: {imported_puz:?}"),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            std::process::exit(1)
+        }
+    }
 }
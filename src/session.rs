@@ -0,0 +1,227 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::api_types::{Pos, TypingDir};
+use crate::import_puz::ImportedPuz;
+use crate::util::invalid_data;
+use crate::state::{Board, Cell, SolveTimer};
+
+/// Progress on a puzzle, autosaved so that solving can resume after the app is closed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub puzzle_hash: String,
+    /// One string per row: '.' for blocks, '-' for empty cells, otherwise the letter
+    pub fill: Vec<String>,
+    pub pencil_cells: Vec<Pos>,
+    pub checked_wrong: Vec<Pos>,
+    pub revealed: Vec<Pos>,
+    pub elapsed: Duration,
+    pub checks_used: usize,
+    pub reveals_used: usize,
+    pub cur_pos: Pos,
+    pub cur_dir: TypingDir,
+    pub pencil: bool,
+}
+
+impl Session {
+    pub fn from_board(puzzle_hash: &str, board: &Board) -> Self {
+        let fill = board.content.iter()
+            .map(|row| row.iter().map(|cell| match cell {
+                Cell::Black => '.',
+                Cell::Empty => '-',
+                Cell::OccupiedRight(ch) | Cell::OccupiedWrong(ch) | Cell::Pencil(ch) => *ch,
+            }).collect())
+            .collect();
+
+        let positions = |pred: &dyn Fn(&Pos) -> bool| -> Vec<Pos> {
+            board.content.iter().enumerate()
+                .flat_map(|(r_idx, row)| (0..row.len()).map(move |c_idx| Pos::new(r_idx, c_idx)))
+                .filter(|pos| pred(pos))
+                .collect()
+        };
+
+        Session {
+            puzzle_hash: puzzle_hash.to_string(),
+            fill,
+            pencil_cells: positions(&|pos| matches!(board.cell_at(pos), Some(Cell::Pencil(_)))),
+            checked_wrong: positions(&|pos| board.marks_at(pos).checked_wrong),
+            revealed: positions(&|pos| board.marks_at(pos).revealed),
+            elapsed: board.timer.elapsed(),
+            checks_used: board.checks_used,
+            reveals_used: board.reveals_used,
+            cur_pos: board.cur_pos,
+            cur_dir: board.cur_dir,
+            pencil: board.pencil,
+        }
+    }
+
+    /// Restores the saved progress on a board freshly loaded from the same puzzle
+    pub fn apply_to(&self, board: &mut Board) {
+        for (r_idx, row) in self.fill.iter().enumerate().take(board.height()) {
+            for (c_idx, ch) in row.chars().enumerate().take(board.width()) {
+                let pos = Pos::new(r_idx, c_idx);
+                if board.is_black_cell(&pos) {
+                    continue
+                }
+                board.content[r_idx][c_idx] = match ch {
+                    '-' | '.' => Cell::Empty,
+                    ch if self.pencil_cells.contains(&pos) => Cell::Pencil(ch),
+                    ch => board.inked_cell(&pos, ch),
+                };
+            }
+        }
+
+        for (r_idx, marks_row) in board.marks.iter_mut().enumerate() {
+            for (c_idx, marks) in marks_row.iter_mut().enumerate() {
+                let pos = Pos::new(r_idx, c_idx);
+                marks.checked_wrong = self.checked_wrong.contains(&pos);
+                marks.revealed = self.revealed.contains(&pos);
+            }
+        }
+
+        board.timer = SolveTimer::started_at(self.elapsed);
+        board.checks_used = self.checks_used;
+        board.reveals_used = self.reveals_used;
+        board.pencil = self.pencil;
+        board.select(self.cur_pos, self.cur_dir);
+        board.update_completion();
+    }
+
+    /// Whether both hold the same fill, marks and cursor, whatever the time spent
+    pub fn same_progress(&self, other: &Session) -> bool {
+        Session { elapsed: other.elapsed, ..self.clone() } == *other
+    }
+
+    /// Where the session for the puzzle with hash `puzzle_hash` lives
+    pub fn path(puzzle_hash: &str) -> io::Result<PathBuf> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user data directory"))?;
+        Ok(data_dir.join("cross-iced").join("sessions").join(format!("{puzzle_hash}.json")))
+    }
+
+    /// The saved session for the puzzle, None when there is none yet
+    pub fn load(puzzle_hash: &str) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(Self::path(puzzle_hash)?) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err)
+        };
+
+        let root: Value = serde_json::from_str(&text).map_err(invalid_data)?;
+        Self::from_json(&root).map(Some)
+    }
+
+    /// Writes to a temporary file and renames it, so that a crash midway never leaves
+    /// a truncated session behind
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path(&self.puzzle_hash)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        let written = File::create(&tmp_path).and_then(|mut file| {
+            file.write_all(self.to_json().to_string().as_bytes())?;
+            file.sync_all()
+        });
+        match written {
+            Ok(()) => fs::rename(&tmp_path, &path),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp_path);
+                Err(err)
+            }
+        }
+    }
+
+    /// Deletes the saved session, so that the puzzle starts fresh next time
+    pub fn remove(puzzle_hash: &str) -> io::Result<()> {
+        match fs::remove_file(Self::path(puzzle_hash)?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(())
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "puzzle_hash": self.puzzle_hash,
+            "fill": self.fill,
+            "pencil_cells": positions_to_json(&self.pencil_cells),
+            "checked_wrong": positions_to_json(&self.checked_wrong),
+            "revealed": positions_to_json(&self.revealed),
+            "elapsed_secs": self.elapsed.as_secs_f64(),
+            "checks_used": self.checks_used,
+            "reveals_used": self.reveals_used,
+            "cursor": [self.cur_pos.row, self.cur_pos.col],
+            "direction": match self.cur_dir {
+                TypingDir::Across => "across",
+                TypingDir::Down => "down",
+            },
+            "pencil": self.pencil,
+        })
+    }
+
+    fn from_json(root: &Value) -> io::Result<Self> {
+        let fill = root["fill"].as_array()
+            .ok_or_else(|| invalid_data("session: no fill"))?
+            .iter()
+            .map(|row| row.as_str().unwrap_or("").to_string())
+            .collect();
+
+        let cur_dir = match root["direction"].as_str() {
+            Some("down") => TypingDir::Down,
+            _ => TypingDir::Across
+        };
+
+        Ok(Session {
+            puzzle_hash: root["puzzle_hash"].as_str().unwrap_or("").to_string(),
+            fill,
+            pencil_cells: positions_from_json(&root["pencil_cells"]),
+            checked_wrong: positions_from_json(&root["checked_wrong"]),
+            revealed: positions_from_json(&root["revealed"]),
+            elapsed: Duration::from_secs_f64(root["elapsed_secs"].as_f64().unwrap_or(0.).max(0.)),
+            checks_used: root["checks_used"].as_u64().unwrap_or(0) as usize,
+            reveals_used: root["reveals_used"].as_u64().unwrap_or(0) as usize,
+            cur_pos: pos_from_json(&root["cursor"]).unwrap_or(Pos::new(0, 0)),
+            cur_dir,
+            pencil: root["pencil"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+/// Identifies a puzzle by its grid and clues, independent of the file it was loaded from
+/// and of the progress saved in it
+pub fn puzzle_hash(puz: &ImportedPuz) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((puz.width() as u32).to_le_bytes());
+    hasher.update((puz.height() as u32).to_le_bytes());
+    for r_idx in 0..puz.height() {
+        for c_idx in 0..puz.width() {
+            hasher.update(puz.solution_at(r_idx, c_idx).to_string().as_bytes());
+        }
+    }
+    for clue in puz.clues() {
+        hasher.update(clue.as_bytes());
+        hasher.update([0]);
+    }
+
+    hasher.finalize().iter().map(|by| format!("{by:02x}")).collect()
+}
+
+fn positions_to_json(positions: &[Pos]) -> Value {
+    positions.iter().map(|pos| json!([pos.row, pos.col])).collect()
+}
+
+fn positions_from_json(value: &Value) -> Vec<Pos> {
+    value.as_array().into_iter().flatten().filter_map(pos_from_json).collect()
+}
+
+fn pos_from_json(value: &Value) -> Option<Pos> {
+    let row = value.get(0)?.as_u64()?;
+    let col = value.get(1)?.as_u64()?;
+    Some(Pos::new(row as usize, col as usize))
+}
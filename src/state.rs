@@ -1,12 +1,16 @@
 use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
 
-use crate::{api_types::*, import_puz:: Pos2ClueIdx};
+use crate::{api_types::*, import_puz::{CellNumbers, ClueGroups, ImportedPuz, Marker, Pos2ClueIdx, GEXT_INCORRECT, GEXT_REVEALED}};
+use crate::history::{CellChange, Edit, History};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cell {
     Empty,
     OccupiedRight(char),
     OccupiedWrong(char),
+    /// Tentative letter, ignored by checks until inked
+    Pencil(char),
     Black,
 }
 
@@ -16,14 +20,90 @@ impl Cell {
             Self::Empty => ' ',
             Self::Black => ' ',
             Self::OccupiedRight(c) => *c,
-            Self::OccupiedWrong(c) => *c
+            Self::OccupiedWrong(c) => *c,
+            Self::Pencil(c) => *c,
         }
     }
 }
 
+/// Per-cell flags kept alongside the letters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellMarks {
+    /// A check found the letter wrong; cleared when the cell is edited
+    pub checked_wrong: bool,
+    /// The letter was revealed from the solution; stays set for the rest of the solve
+    pub revealed: bool,
+}
+
+/// Cell counts for solve statistics; revealed cells never count as solved cleanly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    pub open_cells: usize,
+    /// Cells holding a letter, pencilled ones included
+    pub filled: usize,
+    pub correct: usize,
+    pub revealed: usize,
+    pub clean: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    InProgress,
+    /// Every square is filled in, some only in pencil
+    Pencilled,
+    /// Every square is filled in, but this many are wrong
+    Incorrect(usize),
+    Solved,
+}
+
+/// Knobs for how the cursor moves while solving
+#[derive(Debug, Clone, Copy)]
+pub struct SolveOptions {
+    /// Next / previous clue pass over entries that are already filled in
+    pub skip_full_entries: bool,
+    /// After typing a letter, skip the squares of the word that are already filled in
+    pub skip_filled_cells: bool,
+    /// After typing the last open square of a word, jump to the next clue
+    pub jump_to_next_entry: bool,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        SolveOptions {
+            skip_full_entries: true,
+            skip_filled_cells: true,
+            jump_to_next_entry: true,
+        }
+    }
+}
+
+/// Solving time, stopped once the puzzle is solved
+#[derive(Debug, Clone, Copy)]
+pub struct SolveTimer {
+    accumulated: Duration,
+    running_since: Option<Instant>,
+}
+
+impl SolveTimer {
+    pub fn started_at(accumulated: Duration) -> Self {
+        SolveTimer { accumulated, running_since: Some(Instant::now()) }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.accumulated + self.running_since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+
+    pub fn stop(&mut self) {
+        self.accumulated = self.elapsed();
+        self.running_since = None;
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SelectedWord {
-    poss: BTreeSet<Pos>
+    poss: BTreeSet<Pos>,
+    // start of the entry under the cursor, poss also holds linked entries
+    start: Option<Pos>,
 }
 
 impl SelectedWord {
@@ -31,19 +111,68 @@ impl SelectedWord {
         self.poss.contains(pos)
     }
     pub fn first_pos(&self) -> Option<Pos> {
-        self.poss.first().cloned()
+        self.start
+    }
+    pub fn cells(&self) -> impl Iterator<Item = &Pos> {
+        self.poss.iter()
+    }
+}
+
+/// A clue together with the entry it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clue {
+    pub number: usize,
+    pub dir: TypingDir,
+    pub start: Pos,
+    /// Cells of the entry, of all linked entries for a multi-part clue
+    pub cells: Vec<Pos>,
+    pub text: String,
+}
+
+impl Clue {
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Short label such as "14A (5)"
+    pub fn header(&self) -> String {
+        let dir_letter = match self.dir {
+            TypingDir::Across => 'A',
+            TypingDir::Down => 'D',
+        };
+        format!("{}{dir_letter} ({})", self.number, self.len())
     }
 }
 
+/// The solving model: grid contents, cursor and clues, independent of any UI.
 pub struct Board {
     pub content: Vec<Vec<Cell>>,
     pub solution: Vec<Vec<Cell>>,
     pub clues: Vec<String>,
     pub pos_2_clue_idx: Pos2ClueIdx,
+    pub clue_groups: ClueGroups,
+    pub cell_numbers: CellNumbers,
     pub cur_pos: Pos, // row idx, col idx
     pub cur_dir: TypingDir,
     pub cur_sel: SelectedWord,// BTreeSet<Pos>,
     pub title: String,
+    pub history: History,
+    pub marks: Vec<Vec<CellMarks>>,
+    /// Circles, bars and triangles of the puzzle, per cell
+    pub markers: Vec<Vec<Vec<Marker>>>,
+    /// Show wrong letters as they are typed
+    pub autocheck: bool,
+    /// Typed letters go in as `Cell::Pencil`
+    pub pencil: bool,
+    pub completion: Completion,
+    pub timer: SolveTimer,
+    pub checks_used: usize,
+    pub reveals_used: usize,
+    pub options: SolveOptions,
 }
 
 impl Board {
@@ -52,7 +181,11 @@ impl Board {
         content: Vec<Vec<Cell>>,
         solution: Vec<Vec<Cell>>,  
         clues: Vec<String>, 
-        pos_2_clue_idx: Pos2ClueIdx) -> Self {
+        pos_2_clue_idx: Pos2ClueIdx,
+        clue_groups: ClueGroups,
+        cell_numbers: CellNumbers) -> Self {
+        let marks = content.iter().map(|row| vec![CellMarks::default(); row.len()]).collect();
+        let markers = content.iter().map(|row| vec![Vec::new(); row.len()]).collect();
         let mut ret = Board {
             content,
             solution,
@@ -61,22 +194,315 @@ impl Board {
             cur_dir: TypingDir::Across,
             cur_sel: SelectedWord::default(),
             clues,
-            pos_2_clue_idx: pos_2_clue_idx,
+            pos_2_clue_idx,
+            clue_groups,
+            cell_numbers,
+            history: History::default(),
+            marks,
+            markers,
+            autocheck: false,
+            pencil: false,
+            completion: Completion::InProgress,
+            timer: SolveTimer::started_at(Duration::ZERO),
+            checks_used: 0,
+            reveals_used: 0,
+            options: SolveOptions::default(),
         };
         
         ret.update_selection();
+        ret.update_completion();
         ret
     }
+
+    /// Board for an imported puzzle, starting from the player state saved in it
+    pub fn from_puzzle(title: String, imported_puz: &ImportedPuz) -> Self {
+        let solution = solution_from(imported_puz);
+        let content = content_from(imported_puz, &solution);
+
+        let mut board = Board::new(
+            title,
+            content,
+            solution,
+            imported_puz.clues().clone(),
+            imported_puz.pos_2_clue_idx.clone(),
+            imported_puz.clue_groups.clone(),
+            imported_puz.cell_numbers(),
+        );
+
+        for (r_idx, marks_row) in board.marks.iter_mut().enumerate() {
+            for (c_idx, marks) in marks_row.iter_mut().enumerate() {
+                let bits = imported_puz.markup_at(r_idx, c_idx);
+                marks.checked_wrong = bits & GEXT_INCORRECT != 0;
+                marks.revealed = bits & GEXT_REVEALED != 0;
+            }
+        }
+        for (r_idx, markers_row) in board.markers.iter_mut().enumerate() {
+            for (c_idx, markers) in markers_row.iter_mut().enumerate() {
+                *markers = imported_puz.markers_at(r_idx, c_idx);
+            }
+        }
+
+        board.move_to_first_open_cell();
+        board.update_completion();
+        board
+    }
+
+    /// Writes the fill and the checked / revealed flags into `imported_puz`, ready to be saved
+    pub fn store_in_puzzle(&self, imported_puz: &mut ImportedPuz) {
+        for pos in self.all_positions() {
+            let ch = match self.content[pos.row][pos.col] {
+                Cell::Black => '.',
+                Cell::Empty => '-',
+                Cell::OccupiedRight(c) | Cell::OccupiedWrong(c) | Cell::Pencil(c) => c,
+            };
+            imported_puz.set_player_state_at(pos.row, pos.col, ch);
+
+            let marks = self.marks_at(&pos);
+            let mut bits = imported_puz.markup_at(pos.row, pos.col) & !(GEXT_INCORRECT | GEXT_REVEALED);
+            if marks.checked_wrong {
+                bits |= GEXT_INCORRECT
+            }
+            if marks.revealed {
+                bits |= GEXT_REVEALED
+            }
+            if bits != imported_puz.markup_at(pos.row, pos.col) {
+                imported_puz.set_markup_at(pos.row, pos.col, bits)
+            }
+        }
+    }
     
     pub fn height(&self) -> usize {
         self.content.len()
     }
 
     pub fn width(&self) -> usize {
-        if !self.content.is_empty() {
-            self.content[0].len()
+        self.content.first().map(|row| row.len()).unwrap_or(0)
+    }
+
+    pub fn cell_at(&self, pos: &Pos) -> Option<Cell> {
+        self.content.get(pos.row).and_then(|row| row.get(pos.col)).cloned()
+    }
+
+    /// Clue number printed in the cell at `pos`, if an entry starts there
+    pub fn number_at(&self, pos: &Pos) -> Option<usize> {
+        self.cell_numbers.number_at(pos)
+    }
+
+    pub fn selected_word(&self) -> &SelectedWord {
+        &self.cur_sel
+    }
+
+    /// Moves the cursor to `pos`, ignored for black cells and positions off the grid
+    pub fn set_cursor(&mut self, pos: Pos) {
+        if self.solution_tile_at(&pos).is_some() && !self.is_black_cell(&pos) {
+            self.cur_pos = pos;
+            self.update_selection()
+        }
+    }
+
+    /// Moves the cursor to `pos` and selects the entry going in `dir`
+    pub fn select(&mut self, pos: Pos, dir: TypingDir) {
+        self.cur_dir = dir;
+        self.set_cursor(pos);
+        self.update_selection()
+    }
+
+    /// Mouse selection: clicking the cursor cell toggles the direction, clicking another cell
+    /// moves there, switching direction when the cell is only part of an entry in the other one
+    pub fn click_cell(&mut self, pos: Pos) {
+        if pos == self.cur_pos {
+            self.toggle_typing_dir();
+            return
+        }
+
+        let dir = self.cur_dir;
+        if self.entry_start(&pos, dir).is_none() && self.entry_start(&pos, dir.toggle()).is_some() {
+            self.select(pos, dir.toggle())
+        } else {
+            self.set_cursor(pos)
+        }
+    }
+
+    /// Start of the entry going in `dir` through `pos`, if there is such an entry
+    pub fn entry_start(&self, pos: &Pos, dir: TypingDir) -> Option<Pos> {
+        let mut cells = BTreeSet::new();
+        let (_, delta_back) = self.dir_deltas(dir);
+        self.extend_selection_dir(&mut cells, *pos, &delta_back);
+
+        let start = cells.first().cloned()?;
+        self.pos_2_clue_idx.0.contains_key(&(start, dir)).then_some(start)
+    }
+
+    /// Whether the letter at `pos` matches the solution, None for empty and black cells
+    /// and where the solution is unknown
+    pub fn check_cell(&self, pos: &Pos) -> Option<bool> {
+        if !self.solution_known(pos) {
+            return None
+        }
+        match self.cell_at(pos)? {
+            Cell::OccupiedRight(_) => Some(true),
+            Cell::OccupiedWrong(_) => Some(false),
+            Cell::Empty | Cell::Black | Cell::Pencil(_) => None,
+        }
+    }
+
+    /// False for open cells whose answer the puzzle doesn't give, e.g. in prize puzzles
+    pub fn solution_known(&self, pos: &Pos) -> bool {
+        self.solution_tile_at(pos).is_some_and(|tile| tile != Cell::Empty)
+    }
+
+    /// Checks, reveals and completion only work when every answer is known
+    pub fn has_solution(&self) -> bool {
+        self.all_positions().all(|pos| self.solution_known(&pos))
+    }
+
+    pub fn marks_at(&self, pos: &Pos) -> CellMarks {
+        self.marks.get(pos.row).and_then(|row| row.get(pos.col)).cloned().unwrap_or_default()
+    }
+
+    pub fn markers_at(&self, pos: &Pos) -> &[Marker] {
+        self.markers.get(pos.row).and_then(|row| row.get(pos.col)).map_or(&[], |markers| markers.as_slice())
+    }
+
+    /// Whether the letter is flagged wrong as typed, which only happens with autocheck on
+    pub fn autocheck_wrong(&self, pos: &Pos) -> bool {
+        self.autocheck && self.check_cell(pos) == Some(false)
+    }
+
+    pub fn toggle_autocheck(&mut self) {
+        self.autocheck = !self.autocheck
+    }
+
+    pub fn check_square(&mut self) -> usize {
+        self.check_cells(vec![self.cur_pos])
+    }
+
+    pub fn check_word(&mut self) -> usize {
+        let cells = self.cur_sel.cells().cloned().collect();
+        self.check_cells(cells)
+    }
+
+    pub fn check_puzzle(&mut self) -> usize {
+        let cells = self.all_positions().collect();
+        self.check_cells(cells)
+    }
+
+    /// Marks the wrong letters among `cells`, returns how many were wrong
+    fn check_cells(&mut self, cells: Vec<Pos>) -> usize {
+        if self.is_solved() || !self.has_solution() {
+            return 0
+        }
+        self.checks_used += 1;
+
+        let mut num_wrong = 0;
+        for pos in cells {
+            if self.check_cell(&pos) == Some(false) {
+                self.marks[pos.row][pos.col].checked_wrong = true;
+                num_wrong += 1
+            }
+        }
+        num_wrong
+    }
+
+    pub fn reveal_square(&mut self) {
+        self.reveal_cells(vec![self.cur_pos])
+    }
+
+    pub fn reveal_word(&mut self) {
+        let cells = self.cur_sel.cells().cloned().collect();
+        self.reveal_cells(cells)
+    }
+
+    pub fn reveal_puzzle(&mut self) {
+        let cells = self.all_positions().collect();
+        self.reveal_cells(cells)
+    }
+
+    /// Copies the solution into `cells` as one undo step, flagging the ones that weren't right
+    fn reveal_cells(&mut self, cells: Vec<Pos>) {
+        if self.is_solved() || !self.has_solution() {
+            return
+        }
+        self.reveals_used += 1;
+
+        let to_reveal: Vec<Pos> = cells.into_iter()
+            .filter(|pos| !self.is_black_cell(pos) && self.check_cell(pos) != Some(true))
+            .collect();
+
+        let changes = to_reveal.iter().map(|pos| (*pos, self.solution[pos.row][pos.col])).collect();
+        self.record_edit(changes, true);
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.completion == Completion::Solved
+    }
+
+    /// Compares the whole grid to the solution; pencilled letters have to be inked first
+    fn calc_completion(&self) -> Completion {
+        if !self.has_solution() {
+            return Completion::InProgress
+        }
+
+        let mut num_wrong = 0;
+        let mut pencilled = false;
+        for pos in self.all_positions().filter(|pos| !self.is_black_cell(pos)) {
+            let cell = self.content[pos.row][pos.col];
+            if cell == Cell::Empty {
+                return Completion::InProgress
+            }
+            pencilled |= matches!(cell, Cell::Pencil(_));
+            if cell.get_char() != self.solution[pos.row][pos.col].get_char() {
+                num_wrong += 1
+            }
+        }
+
+        if self.height() == 0 {
+            Completion::InProgress
+        } else if pencilled {
+            Completion::Pencilled
+        } else if num_wrong > 0 {
+            Completion::Incorrect(num_wrong)
         } else {
-            panic!("board not initialized?!")
+            Completion::Solved
+        }
+    }
+
+    pub(crate) fn update_completion(&mut self) {
+        self.completion = self.calc_completion();
+        if self.is_solved() {
+            self.timer.stop()
+        }
+    }
+
+    pub fn solve_stats(&self) -> SolveStats {
+        let mut stats = SolveStats::default();
+        for pos in self.all_positions().filter(|pos| !self.is_black_cell(pos)) {
+            let correct = self.check_cell(&pos) == Some(true);
+            let revealed = self.marks_at(&pos).revealed;
+
+            stats.open_cells += 1;
+            stats.filled += (self.cell_at(&pos) != Some(Cell::Empty)) as usize;
+            stats.correct += correct as usize;
+            stats.revealed += revealed as usize;
+            stats.clean += (correct && !revealed) as usize;
+        }
+        stats
+    }
+
+    pub fn wrong_cells(&self) -> Vec<Pos> {
+        self.all_positions()
+            .filter(|pos| self.check_cell(pos) == Some(false))
+            .collect()
+    }
+
+    fn all_positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        (0..self.height()).flat_map(move |r_idx| (0..self.width()).map(move |c_idx| Pos::new(r_idx, c_idx)))
+    }
+
+    fn move_to_first_open_cell(&mut self) {
+        let first_open = self.all_positions().find(|pos| !self.is_black_cell(pos));
+        if let Some(pos) = first_open {
+            self.set_cursor(pos)
         }
     }
     
@@ -90,7 +516,7 @@ impl Board {
         let width = self.width();
 
         if height == 0 || width == 0 {
-            panic!("board not initialized?!")
+            return false
         }
 
         let mut result = self.cur_pos;
@@ -129,27 +555,221 @@ impl Board {
     }
 
     pub fn type_letter(&mut self, ch: char, move_next: bool) {
-        let ri = self.cur_pos.row;
-        let ci = self.cur_pos.col;
-        self.content[ri][ci] = if ch != ' '  {
-            let right = self.solution[ri][ci].get_char();
+        if self.cell_at(&self.cur_pos).is_none() || self.is_black_cell(&self.cur_pos) {
+            return
+        }
 
-            if ch == right {
-                Cell::OccupiedRight(ch)
-            } else {
-                Cell::OccupiedWrong(ch)
-            }
+        let new_cell = self.typed_cell(&self.cur_pos, ch);
+        self.apply_edit(vec![(self.cur_pos, new_cell)]);
+        
+        if move_next {
+            self.advance_after_typing()
+        }
+    }
+
+    /// Moves on within the current word, never wrapping into another row or column.
+    /// At the end of the word, goes to the next clue if so configured.
+    fn advance_after_typing(&mut self) {
+        let Some(start) = self.cur_sel.first_pos() else {
+            return
+        };
+        let cells = self.entry_cells(&start, self.cur_dir);
+        let Some(cur_idx) = cells.iter().position(|pos| *pos == self.cur_pos) else {
+            return
+        };
+
+        let is_empty = |pos: &&Pos| self.cell_at(pos) == Some(Cell::Empty);
+        let next = if self.options.skip_filled_cells {
+            // first open square after the cursor, else an earlier one left open in the word,
+            // else (the word is full) simply the next square
+            cells[cur_idx + 1..].iter().find(is_empty)
+                .or_else(|| cells[..cur_idx].iter().find(is_empty))
+                .or_else(|| cells.get(cur_idx + 1))
         } else {
+            cells.get(cur_idx + 1)
+        };
+
+        // only typing the last square of the word leaves it
+        match next {
+            Some(pos) => {
+                let pos = *pos;
+                self.set_cursor(pos)
+            },
+            None if self.options.jump_to_next_entry => self.next_clue(),
+            None => {}
+        }
+    }
+
+    pub fn clear_cell(&mut self) {
+        self.type_letter(' ', false)
+    }
+
+    /// Clears the current cell; if it is already empty, moves back one cell and clears that.
+    /// From the start of a word, backing up goes to the end of the previous clue.
+    pub fn backspace(&mut self) {
+        if self.cell_at(&self.cur_pos) == Some(Cell::Empty) {
+            self.step_in_word(-1)
+        }
+        self.clear_cell()
+    }
+
+    /// Moves one cell forward in the typing direction without erasing
+    pub fn advance(&mut self) {
+        self.step_in_word(1)
+    }
+
+    /// Moves `step` cells along the current word, continuing into the adjacent clue at its ends
+    fn step_in_word(&mut self, step: i32) {
+        let Some(start) = self.cur_sel.first_pos() else {
+            return
+        };
+        let cells = self.entry_cells(&start, self.cur_dir);
+        let Some(cur_idx) = cells.iter().position(|pos| *pos == self.cur_pos) else {
+            return
+        };
+
+        let new_idx = cur_idx as i32 + step;
+        if new_idx >= 0 && (new_idx as usize) < cells.len() {
+            self.set_cursor(cells[new_idx as usize])
+        } else {
+            self.goto_adjacent_entry(step)
+        }
+    }
+
+    /// Goes to the first (`step` > 0) or last (`step` < 0) cell of the adjacent clue
+    fn goto_adjacent_entry(&mut self, step: i32) {
+        let entries = self.entries_in_order();
+        let Some(cur_idx) = self.cur_entry_idx(&entries) else {
+            return
+        };
+
+        let (start, dir) = entries[(cur_idx as i32 + step).rem_euclid(entries.len() as i32) as usize];
+        let cells = self.entry_cells(&start, dir);
+        let target = if step > 0 { cells.first() } else { cells.last() };
+        if let Some(pos) = target {
+            self.select(*pos, dir)
+        }
+    }
+
+    /// Clears every cell of the selected word, as a single undo step
+    pub fn clear_word(&mut self) {
+        let changes = self.cur_sel.cells().map(|pos| (*pos, Cell::Empty)).collect();
+        self.apply_edit(changes)
+    }
+
+    pub fn toggle_pencil(&mut self) {
+        self.pencil = !self.pencil
+    }
+
+    /// Confirms the pencilled letters of the selected word, as a single undo step
+    pub fn ink_word(&mut self) {
+        let changes = self.cur_sel.cells()
+            .filter_map(|pos| match self.cell_at(pos) {
+                Some(Cell::Pencil(ch)) => Some((*pos, self.inked_cell(pos, ch))),
+                _ => None
+            })
+            .collect();
+        self.apply_edit(changes)
+    }
+
+    /// Cell resulting from typing `ch` at `pos`, a space clears it
+    fn typed_cell(&self, pos: &Pos, ch: char) -> Cell {
+        if ch == ' ' {
             Cell::Empty
+        } else if self.pencil {
+            Cell::Pencil(ch)
+        } else {
+            self.inked_cell(pos, ch)
+        }
+    }
+
+    pub(crate) fn inked_cell(&self, pos: &Pos, ch: char) -> Cell {
+        let right = self.solution[pos.row][pos.col].get_char();
+        if ch == right {
+            Cell::OccupiedRight(ch)
+        } else {
+            Cell::OccupiedWrong(ch)
+        }
+    }
+
+    /// Writes `changes` to the grid and records them as one undoable step.
+    /// The grid is locked once solved.
+    fn apply_edit(&mut self, changes: Vec<(Pos, Cell)>) {
+        self.record_edit(changes, false)
+    }
+
+    /// `apply_edit`, flagging the changed cells as revealed when `reveal` is set. The marks
+    /// are part of the edit, so undoing a reveal drops the flag again.
+    fn record_edit(&mut self, changes: Vec<(Pos, Cell)>, reveal: bool) {
+        if self.is_solved() {
+            return
+        }
+
+        let changes: Vec<CellChange> = changes.into_iter()
+            .filter_map(|(pos, after)| {
+                let before = self.cell_at(&pos)?;
+                let before_marks = self.marks_at(&pos);
+                // editing a cell drops its check result
+                let after_marks = CellMarks { checked_wrong: false, revealed: before_marks.revealed || reveal };
+                (before != after && before != Cell::Black)
+                    .then_some(CellChange { pos, before, after, before_marks, after_marks })
+            })
+            .collect();
+
+        if changes.is_empty() {
+            return
+        }
+
+        for change in changes.iter() {
+            self.set_cell(&change.pos, change.after, change.after_marks);
+        }
+        self.history.record(Edit { changes, cur_pos: self.cur_pos, cur_dir: self.cur_dir });
+        self.update_completion();
+    }
+
+    /// Reverts the last edit, returns false when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        if self.is_solved() {
+            return false
+        }
+        let Some(edit) = self.history.undo() else {
+            return false
         };
-        
-        let dir = match self.cur_dir {
-            TypingDir::Across => Direction::Right,
-            TypingDir::Down => Direction::Down
+
+        for change in edit.changes.iter() {
+            self.set_cell(&change.pos, change.before, change.before_marks);
+        }
+        self.restore_cursor(&edit);
+        self.update_completion();
+        true
+    }
+
+    /// Re-applies the last undone edit, returns false when there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        if self.is_solved() {
+            return false
+        }
+        let Some(edit) = self.history.redo() else {
+            return false
         };
-        if move_next {
-            self.move_cursor_until_not_black(dir)
+
+        for change in edit.changes.iter() {
+            self.set_cell(&change.pos, change.after, change.after_marks);
         }
+        self.restore_cursor(&edit);
+        self.update_completion();
+        true
+    }
+
+    fn set_cell(&mut self, pos: &Pos, cell: Cell, marks: CellMarks) {
+        self.content[pos.row][pos.col] = cell;
+        self.marks[pos.row][pos.col] = marks;
+    }
+
+    fn restore_cursor(&mut self, edit: &Edit) {
+        self.cur_pos = edit.cur_pos;
+        self.cur_dir = edit.cur_dir;
+        self.update_selection()
     }
 
     fn solution_tile_at(&self, pos: &Pos) -> Option<Cell> {
@@ -172,33 +792,49 @@ impl Board {
     fn recalc_selection(&self) -> SelectedWord {
         let mut selection = BTreeSet::new();
 
-        let (delta_up, delta_down) = if self.cur_dir == TypingDir::Across {
-            (DeltaPos::new(0, 1), DeltaPos::new(0, -1))
-        } else {
-            (DeltaPos::new(-1, 0), DeltaPos::new(1, 0))
-        };
+        let (delta_up, delta_down) = self.dir_deltas(self.cur_dir);
+
+        self.extend_selection_dir(&mut selection, self.cur_pos, &delta_up);
+        self.extend_selection_dir(&mut selection, self.cur_pos, &delta_down);
 
-        self.extend_selection_dir(&mut selection, &delta_up);
-        self.extend_selection_dir(&mut selection, &delta_down);
+        let start = selection.first().cloned();
 
-        SelectedWord{poss: selection}
+        // Linked entries of a multi-part clue get selected together
+        if let Some(group) = start.and_then(|start| self.clue_groups.group_of(&(start, self.cur_dir))) {
+            for (entry_start, dir) in group {
+                let (delta, _) = self.dir_deltas(*dir);
+                self.extend_selection_dir(&mut selection, *entry_start, &delta);
+            }
+        }
+
+        SelectedWord{poss: selection, start}
+    }
+
+    /// (forward, backward) steps for typing in `dir`
+    fn dir_deltas(&self, dir: TypingDir) -> (DeltaPos, DeltaPos) {
+        if dir == TypingDir::Across {
+            (DeltaPos::new(0, 1), DeltaPos::new(0, -1))
+        } else {
+            (DeltaPos::new(1, 0), DeltaPos::new(-1, 0))
+        }
     }
 
-    fn extend_selection_dir(&self, selection: &mut BTreeSet<Pos>, delta: &DeltaPos) {
-        let mut pos =  self.cur_pos.clone();
+    fn extend_selection_dir(&self, selection: &mut BTreeSet<Pos>, start: Pos, delta: &DeltaPos) {
+        let mut pos = start;
 
         while let Some(tile) = self.solution_tile_at(&pos) {
             if tile == Cell::Black {
                 break
             }
-            selection.insert(pos.clone());
+            selection.insert(pos);
             pos.add_ip(delta);
         }
     }
     
     pub fn move_cursor_until_not_black(&mut self, dir: Direction) {
         let  alter_dir = dir.alternate();
-        loop {
+        // bounded, so that a board without open cells doesn't loop forever
+        for _ in 0..self.height() * self.width() {
             let wrapped = self.move_cursor(dir);
             if wrapped {
                 self.move_cursor(alter_dir);
@@ -209,20 +845,400 @@ impl Board {
         }
     }
     
-    pub fn current_clue(&self) -> String {
-        let first_pos_opt = self.cur_sel.first_pos();
-        if let Some(first_pos) = first_pos_opt {
-            if let Some(clue_idx) = self.pos_2_clue_idx.0.get(&(first_pos, self.cur_dir)) { 
-                format!("{first_pos:?} {d:?} c_idx={ci}\n{clue}", d=self.cur_dir, 
-                    ci=*clue_idx, clue=self.clues[*clue_idx])
-            } else {
-                format!("pos: {first_pos:?}, could not find clue_idx!")
+    /// Moves to the next clue, going on from the last Across clue to the first Down one
+    pub fn next_clue(&mut self) {
+        self.goto_clue(1)
+    }
+
+    pub fn prev_clue(&mut self) {
+        self.goto_clue(-1)
+    }
+
+    /// Entries in navigation order: every Across entry by number, then every Down entry
+    pub fn entries_in_order(&self) -> Vec<(Pos, TypingDir)> {
+        let mut entries: Vec<(Pos, TypingDir)> = self.pos_2_clue_idx.0.keys().cloned().collect();
+        entries.sort_by_key(|(pos, dir)| (*dir, *pos));
+        entries
+    }
+
+    /// Cells of the entry starting at `start` going in `dir`
+    pub fn entry_cells(&self, start: &Pos, dir: TypingDir) -> Vec<Pos> {
+        let mut cells = BTreeSet::new();
+        let (delta, _) = self.dir_deltas(dir);
+        self.extend_selection_dir(&mut cells, *start, &delta);
+        cells.into_iter().collect()
+    }
+
+    fn cur_entry_idx(&self, entries: &[(Pos, TypingDir)]) -> Option<usize> {
+        let cur_entry = (self.cur_sel.first_pos()?, self.cur_dir);
+        entries.iter().position(|entry| *entry == cur_entry)
+    }
+
+    fn goto_clue(&mut self, step: i32) {
+        let entries = self.entries_in_order();
+        if entries.is_empty() {
+            return
+        }
+
+        let cur_idx = self.cur_entry_idx(&entries);
+        let num_entries = entries.len() as i32;
+        let mut idx = match cur_idx {
+            Some(idx) => idx as i32,
+            None if step > 0 => -1,
+            None => 0,
+        };
+
+        for _ in 0..entries.len() {
+            idx = (idx + step).rem_euclid(num_entries);
+            let (start, dir) = entries[idx as usize];
+            let cells = self.entry_cells(&start, dir);
+            let first_empty = cells.iter().find(|pos| self.cell_at(pos) == Some(Cell::Empty)).cloned();
+
+            if first_empty.is_some() || !self.options.skip_full_entries {
+                self.select(first_empty.unwrap_or(start), dir);
+                return
+            }
+        }
+
+        // every entry is full, just go to the adjacent one
+        let idx = cur_idx.map(|idx| idx as i32).unwrap_or(0);
+        let (start, dir) = entries[(idx + step).rem_euclid(num_entries) as usize];
+        self.select(start, dir)
+    }
+
+    pub fn clue_at(&self, entry_start: &Pos, dir: TypingDir) -> Option<&str> {
+        self.pos_2_clue_idx.0.get(&(*entry_start, dir))
+            .and_then(|clue_idx| self.clues.get(*clue_idx))
+            .map(|clue| clue.as_str())
+    }
+
+    /// Every clue going in `dir`, in number order
+    pub fn clue_list(&self, dir: TypingDir) -> Vec<Clue> {
+        self.entries_in_order().into_iter()
+            .filter(|(_, entry_dir)| *entry_dir == dir)
+            .filter_map(|entry| self.clue_for(&entry))
+            .collect()
+    }
+
+    /// The clue of the entry starting at `entry.0` going in `entry.1`
+    pub fn clue_for(&self, entry: &(Pos, TypingDir)) -> Option<Clue> {
+        let (start, dir) = *entry;
+        let number = self.cell_numbers.number_at(&start)?;
+        let text = self.clue_at(&start, dir)?.to_string();
+
+        let cells = match self.clue_groups.group_of(entry) {
+            Some(group) => group.iter()
+                .flat_map(|(start, dir)| self.entry_cells(start, *dir))
+                .collect(),
+            None => self.entry_cells(&start, dir),
+        };
+
+        Some(Clue{number, dir, start, cells, text})
+    }
+
+    /// Whether `entry` is the one being solved, or linked to it in a multi-part clue
+    pub fn is_active_entry(&self, entry: &(Pos, TypingDir)) -> bool {
+        let Some(start) = self.cur_sel.first_pos() else {
+            return false
+        };
+        let cur_entry = (start, self.cur_dir);
+
+        *entry == cur_entry || self.clue_groups.group_of(&cur_entry)
+            .is_some_and(|group| group.contains(entry))
+    }
+
+    /// The entry crossing the current one at the cursor
+    pub fn crossing_entry(&self) -> Option<(Pos, TypingDir)> {
+        let dir = self.cur_dir.toggle();
+        self.entry_start(&self.cur_pos, dir).map(|start| (start, dir))
+    }
+
+    /// Clue of the entry being solved. Every part of a multi-part clue shows the clue
+    /// of the group's first entry.
+    pub fn current_clue(&self) -> Option<Clue> {
+        let cur_entry = (self.cur_sel.first_pos()?, self.cur_dir);
+        let entry = self.clue_groups.group_of(&cur_entry)
+            .map(|group| group[0])
+            .unwrap_or(cur_entry);
+
+        self.clue_for(&entry)
+    }
+
+    /// Clues the current clue refers to, e.g. "See 17-Across"
+    pub fn referenced_clues(&self) -> Vec<Clue> {
+        let Some(current) = self.current_clue() else {
+            return Vec::new()
+        };
+
+        let entries = self.entries_in_order();
+        parse_cross_refs(&current.text).into_iter()
+            .filter_map(|(num, dir)| {
+                entries.iter().find(|(start, entry_dir)| {
+                    *entry_dir == dir && self.cell_numbers.number_at(start) == Some(num)
+                })
+            })
+            .filter_map(|entry| self.clue_for(entry))
+            .filter(|clue| clue.start != current.start || clue.dir != current.dir)
+            .collect()
+    }
+
+    /// Clue of the entry crossing the current one at the cursor
+    pub fn crossing_clue(&self) -> Option<Clue> {
+        let crossing = self.crossing_entry()?;
+        let entry = self.clue_groups.group_of(&crossing)
+            .map(|group| group[0])
+            .unwrap_or(crossing);
+
+        self.clue_for(&entry)
+    }
+}
+
+/// Finds references to other entries in a clue's text: "See 17-Across", "With 34- and 52-Down",
+/// "17-, 23- and 45-Across". Numbers are collected until a direction word follows them.
+pub fn parse_cross_refs(text: &str) -> Vec<(usize, TypingDir)> {
+    let mut ret = Vec::new();
+    let mut pending = Vec::new();
+
+    let mut chars = text.chars().peekable();
+    while let Some(&ch) = chars.peek() {
+        if ch.is_ascii_digit() {
+            let mut num = String::new();
+            while let Some(digit) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                num.push(digit)
+            }
+            pending.extend(num.parse::<usize>().ok());
+        } else if ch.is_alphabetic() {
+            let mut word = String::new();
+            while let Some(letter) = chars.next_if(|ch| ch.is_alphabetic()) {
+                word.push(letter)
+            }
+            match word.to_lowercase().as_str() {
+                "across" => ret.extend(pending.drain(..).map(|num| (num, TypingDir::Across))),
+                "down" => ret.extend(pending.drain(..).map(|num| (num, TypingDir::Down))),
+                "and" | "or" => (),
+                _ => pending.clear(),
             }
         } else {
-            format!("No first pos for current selection? cur_sel={:?}", self.cur_sel)
+            chars.next();
         }
     }
+
+    ret
+}
+
+fn solution_from(imported_puz: &ImportedPuz) -> Vec<Vec<Cell>> {
+    let mut ret = Vec::new();
+
+    for r_idx in 0..imported_puz.height() {
+        let row_ret = (0..imported_puz.width()).map (
+            |c_idx| {
+                match imported_puz.solution_at(r_idx, c_idx) {
+                    '.' => Cell::Black,
+                    // the answer is not known
+                    '-' | '_' => Cell::Empty,
+                    c => Cell::OccupiedRight(c)
+                }
+        });
+        ret.push(row_ret.collect())
+    }
+
+    ret
+}
+
+fn content_from(imported_puz: &ImportedPuz, solution: &[Vec<Cell>]) -> Vec<Vec<Cell>> {
+    let mut ret = Vec::new();
+
+    for (r_idx, solution_row) in solution.iter().enumerate() {
+        let row_ret = solution_row.iter().enumerate().map (
+            |(c_idx, right)| {
+                match imported_puz.player_state_at(r_idx, c_idx) {
+                    '.' => Cell::Black,
+                    '-' | '_' => Cell::Empty,
+                    c if c == right.get_char() => Cell::OccupiedRight(c),
+                    c => Cell::OccupiedWrong(c)
+                }
+        });
+        ret.push(row_ret.collect())
+    }
+
+    ret
 }
 
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::import_puz::PuzStrings;
+
+    /// C A T
+    /// A # E
+    /// B E D
+    fn board() -> Board {
+        let solution = vec![
+            vec!['C', 'A', 'T'],
+            vec!['A', '.', 'E'],
+            vec!['B', 'E', 'D'],
+        ];
+        let player_state = solution.iter()
+            .map(|row| row.iter().map(|ch| if *ch == '.' { '.' } else { '-' }).collect())
+            .collect();
+        let clues = BTreeMap::from([
+            ((1, TypingDir::Across), "Pet".to_string()),
+            ((1, TypingDir::Down), "Taxi".to_string()),
+            ((2, TypingDir::Down), "Talk".to_string()),
+            ((3, TypingDir::Across), "Bunk".to_string()),
+        ]);
+        let puz = ImportedPuz::from_grid(solution, player_state, PuzStrings::default(), &clues, Vec::new());
+        Board::from_puzzle("Test".to_string(), &puz)
+    }
+
+    fn letter_at(board: &Board, row: usize, col: usize) -> char {
+        board.cell_at(&Pos::new(row, col)).unwrap().get_char()
+    }
+
+    #[test]
+    fn typing_moves_along_the_word() {
+        let mut board = board();
+        board.options.jump_to_next_entry = false;
+
+        for ch in "CAT".chars() {
+            board.type_letter(ch, true)
+        }
+        assert_eq!((letter_at(&board, 0, 0), letter_at(&board, 0, 1), letter_at(&board, 0, 2)), ('C', 'A', 'T'));
+        // stays on the last square instead of wrapping into the next row
+        assert_eq!(board.cur_pos, Pos::new(0, 2));
+    }
+
+    #[test]
+    fn typing_skips_filled_squares() {
+        let mut board = board();
+        board.select(Pos::new(0, 1), TypingDir::Across);
+        board.type_letter('A', false);
+
+        board.select(Pos::new(0, 0), TypingDir::Across);
+        board.type_letter('C', true);
+        assert_eq!(board.cur_pos, Pos::new(0, 2));
+    }
+
+    #[test]
+    fn typing_the_last_square_jumps_to_the_next_clue() {
+        let mut board = board();
+        for ch in "CAT".chars() {
+            board.type_letter(ch, true)
+        }
+        assert_eq!((board.cur_pos, board.cur_dir), (Pos::new(2, 0), TypingDir::Across));
+    }
+
+    #[test]
+    fn overwriting_inside_a_full_word_stays_in_the_word() {
+        let mut board = board();
+        for ch in "CAT".chars() {
+            board.type_letter(ch, false);
+            board.advance()
+        }
+
+        board.select(Pos::new(0, 0), TypingDir::Across);
+        board.type_letter('B', true);
+        assert_eq!((board.cur_pos, board.cur_dir), (Pos::new(0, 1), TypingDir::Across));
+        board.type_letter('O', true);
+        assert_eq!((board.cur_pos, board.cur_dir), (Pos::new(0, 2), TypingDir::Across));
+    }
+
+    #[test]
+    fn backspace_clears_then_moves_back() {
+        let mut board = board();
+        board.type_letter('C', true);
+        board.type_letter('A', true);
+        assert_eq!(board.cur_pos, Pos::new(0, 2));
+
+        // empty square: back up one and clear that one
+        board.backspace();
+        assert_eq!(board.cur_pos, Pos::new(0, 1));
+        assert_eq!(letter_at(&board, 0, 1), ' ');
+
+        // filled square after moving back: clear it in place
+        board.select(Pos::new(0, 0), TypingDir::Across);
+        board.backspace();
+        assert_eq!(board.cur_pos, Pos::new(0, 0));
+        assert_eq!(letter_at(&board, 0, 0), ' ');
+    }
+
+    #[test]
+    fn backspace_from_the_start_of_a_word_goes_to_the_previous_clue() {
+        let mut board = board();
+        board.select(Pos::new(2, 0), TypingDir::Across);
+        board.backspace();
+        assert_eq!((board.cur_pos, board.cur_dir), (Pos::new(0, 2), TypingDir::Across));
+    }
+
+    #[test]
+    fn delete_and_space_keep_the_cursor() {
+        let mut board = board();
+        board.type_letter('C', false);
+        board.clear_cell();
+        assert_eq!((board.cur_pos, letter_at(&board, 0, 0)), (Pos::new(0, 0), ' '));
+
+        board.type_letter('C', false);
+        board.advance();
+        assert_eq!((board.cur_pos, letter_at(&board, 0, 0)), (Pos::new(0, 1), 'C'));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_letters_and_cursor() {
+        let mut board = board();
+        board.type_letter('C', true);
+        board.type_letter('A', true);
+
+        assert!(board.undo());
+        assert_eq!((letter_at(&board, 0, 1), board.cur_pos), (' ', Pos::new(0, 1)));
+        assert!(board.undo());
+        assert_eq!((letter_at(&board, 0, 0), board.cur_pos), (' ', Pos::new(0, 0)));
+        assert!(!board.undo());
+
+        assert!(board.redo());
+        assert_eq!(letter_at(&board, 0, 0), 'C');
+
+        // a new edit drops what could be redone
+        board.type_letter('X', false);
+        assert!(!board.redo());
+    }
+
+    #[test]
+    fn clearing_a_word_is_one_undo_step() {
+        let mut board = board();
+        for ch in "CAT".chars() {
+            board.type_letter(ch, false);
+            board.advance()
+        }
+        board.select(Pos::new(0, 0), TypingDir::Across);
+        board.clear_word();
+        assert_eq!(letter_at(&board, 0, 2), ' ');
+
+        assert!(board.undo());
+        assert_eq!((letter_at(&board, 0, 0), letter_at(&board, 0, 2)), ('C', 'T'));
+    }
 
+    #[test]
+    fn undoing_a_reveal_drops_the_revealed_mark() {
+        let mut board = board();
+        board.reveal_square();
+        assert!(board.marks_at(&Pos::new(0, 0)).revealed);
 
+        board.undo();
+        assert!(!board.marks_at(&Pos::new(0, 0)).revealed);
+    }
+
+    #[test]
+    fn cross_refs() {
+        use TypingDir::*;
+
+        assert_eq!(parse_cross_refs("See 14 Across"), vec![(14, Across)]);
+        assert_eq!(parse_cross_refs("With 3 and 5 down, 7 across"), vec![(3, Down), (5, Down), (7, Across)]);
+        assert_eq!(parse_cross_refs("See 4, 5 or 6 Down"), vec![(4, Down), (5, Down), (6, Down)]);
+        assert_eq!(parse_cross_refs("1-Across"), vec![(1, Across)]);
+        // numbers not followed by a direction are no references
+        assert_eq!(parse_cross_refs("Year 2000 bug in 12 lines"), vec![]);
+        assert_eq!(parse_cross_refs("3 men, across the river"), vec![]);
+    }
+}
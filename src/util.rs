@@ -0,0 +1,9 @@
+//! Small helpers shared by the importers and the config readers
+
+use std::error::Error;
+use std::io;
+
+/// An `InvalidData` I/O error, for files that could be read but not understood
+pub(crate) fn invalid_data<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -1 +1,254 @@
+use std::collections::BTreeSet;
 
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse;
+use iced::widget::canvas::{self, event, Cache, Canvas, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Element, Point, Rectangle, Renderer, Size, Theme, Vector};
+
+use crate::api_types::Pos;
+use crate::msg::Msg;
+use crate::import_puz::{Marker, Side};
+use crate::palette::Palette;
+use crate::state::{Board, Cell};
+
+// letters, clue numbers and markers scale with the cell
+const TEXT_SCALE: f32 = 0.6;
+const NUMBER_SCALE: f32 = 0.25;
+const WRONG_FRAME_SCALE: f32 = 0.075;
+const BAR_SCALE: f32 = 0.1;
+const TRIANGLE_SCALE: f32 = 0.18;
+const REVEALED_FLAG_SCALE: f32 = 0.3;
+
+/// The grid drawn on a canvas. The geometry lives in `cache`, which the owner clears
+/// whenever the board or the palette changes; until then redraws reuse it.
+pub struct GridCanvas<'a> {
+    pub board: &'a Board,
+    pub palette: Palette,
+    pub cell_size: f32,
+    pub cache: &'a Cache,
+}
+
+impl<'a> GridCanvas<'a> {
+    pub fn view(self) -> Element<'a, Msg> {
+        let width = self.cell_size * self.board.width() as f32;
+        let height = self.cell_size * self.board.height() as f32;
+        Canvas::new(self).width(width).height(height).into()
+    }
+
+    /// Cell under `point`, relative to the top left corner of the grid
+    fn pos_at(&self, point: Point) -> Option<Pos> {
+        let pos = Pos::new((point.y / self.cell_size) as usize, (point.x / self.cell_size) as usize);
+        (pos.row < self.board.height() && pos.col < self.board.width()).then_some(pos)
+    }
+
+    pub fn draw_grid(&self, frame: &mut Frame) {
+        let board = self.board;
+        let palette = &self.palette;
+        let referenced: BTreeSet<Pos> = board.referenced_clues().into_iter()
+            .flat_map(|clue| clue.cells)
+            .collect();
+
+        frame.fill_rectangle(Point::ORIGIN, frame.size(), palette.background);
+
+        for (r_idx, content_row) in board.content.iter().enumerate() {
+            for (c_idx, tile) in content_row.iter().enumerate() {
+                let pos = Pos::new(r_idx, c_idx);
+                let background = if *tile == Cell::Black {
+                    palette.black
+                } else if pos == board.cur_pos {
+                    palette.cursor
+                } else if board.cur_sel.contains(&pos) {
+                    palette.word
+                } else if referenced.contains(&pos) {
+                    palette.reference
+                } else {
+                    continue
+                };
+                frame.fill_rectangle(self.cell_origin(&pos), Size::new(self.cell_size, self.cell_size), background);
+            }
+        }
+
+        self.draw_grid_lines(frame);
+
+        for (r_idx, content_row) in board.content.iter().enumerate() {
+            for (c_idx, tile) in content_row.iter().enumerate() {
+                let pos = Pos::new(r_idx, c_idx);
+                if *tile == Cell::Black {
+                    continue
+                }
+
+                for marker in board.markers_at(&pos) {
+                    self.draw_marker(frame, &pos, *marker)
+                }
+                if board.marks_at(&pos).checked_wrong {
+                    self.draw_wrong_frame(frame, &pos)
+                }
+                if board.marks_at(&pos).revealed {
+                    self.draw_revealed_flag(frame, &pos)
+                }
+                if let Some(number) = board.number_at(&pos) {
+                    self.draw_number(frame, &pos, number)
+                }
+                match tile {
+                    Cell::OccupiedRight(ch) | Cell::OccupiedWrong(ch) => {
+                        self.draw_letter(frame, &pos, *ch, letter_color(board, &pos, palette))
+                    },
+                    Cell::Pencil(ch) => self.draw_letter(frame, &pos, *ch, palette.pencil),
+                    Cell::Empty | Cell::Black => (),
+                }
+            }
+        }
+    }
+
+    fn cell_origin(&self, pos: &Pos) -> Point {
+        Point::new(pos.col as f32 * self.cell_size, pos.row as f32 * self.cell_size)
+    }
+
+    fn draw_grid_lines(&self, frame: &mut Frame) {
+        let (width, height) = (self.board.width(), self.board.height());
+        let grid_width = width as f32 * self.cell_size;
+        let grid_height = height as f32 * self.cell_size;
+
+        // kept half a pixel inside so that the outer lines are not clipped
+        let line_at = |idx: usize, extent: f32| (idx as f32 * self.cell_size).clamp(0.5, extent - 0.5);
+        let lines = Path::new(|builder| {
+            for r_idx in 0..=height {
+                let y = line_at(r_idx, grid_height);
+                builder.move_to(Point::new(0., y));
+                builder.line_to(Point::new(grid_width, y));
+            }
+            for c_idx in 0..=width {
+                let x = line_at(c_idx, grid_width);
+                builder.move_to(Point::new(x, 0.));
+                builder.line_to(Point::new(x, grid_height));
+            }
+        });
+        frame.stroke(&lines, Stroke::default().with_color(self.palette.grid_lines).with_width(1.));
+    }
+
+    /// Cells found wrong by a check keep a thick frame, whatever their other styling
+    fn draw_wrong_frame(&self, frame: &mut Frame, pos: &Pos) {
+        let width = self.cell_size * WRONG_FRAME_SCALE;
+        let inset = Vector::new(width / 2., width / 2.);
+        let side = self.cell_size - width;
+        let path = Path::rectangle(self.cell_origin(pos) + inset, Size::new(side, side));
+        frame.stroke(&path, Stroke::default().with_color(self.palette.wrong).with_width(width));
+    }
+
+    /// Revealed cells get a flag in the top right corner, so they don't differ by colour only
+    fn draw_revealed_flag(&self, frame: &mut Frame, pos: &Pos) {
+        let corner = self.cell_origin(pos) + Vector::new(self.cell_size, 0.);
+        let side = self.cell_size * REVEALED_FLAG_SCALE;
+        let path = Path::new(|builder| {
+            builder.move_to(corner);
+            builder.line_to(corner + Vector::new(0., side));
+            builder.line_to(corner - Vector::new(side, 0.));
+            builder.close();
+        });
+        frame.fill(&path, self.palette.revealed);
+    }
+
+    fn draw_marker(&self, frame: &mut Frame, pos: &Pos, marker: Marker) {
+        let size = self.cell_size;
+        let origin = self.cell_origin(pos);
+        let center = origin + Vector::new(size / 2., size / 2.);
+        let color = self.palette.grid_lines;
+
+        match marker {
+            Marker::Circle => {
+                let path = Path::circle(center, size / 2. - 1.5);
+                frame.stroke(&path, Stroke::default().with_color(color).with_width(1.));
+            },
+            Marker::Bar(side) => {
+                let thickness = size * BAR_SCALE;
+                let (top_left, bar_size) = match side {
+                    Side::Top => (origin, Size::new(size, thickness)),
+                    Side::Right => (origin + Vector::new(size - thickness, 0.), Size::new(thickness, size)),
+                    Side::Bottom => (origin + Vector::new(0., size - thickness), Size::new(size, thickness)),
+                    Side::Left => (origin, Size::new(thickness, size)),
+                };
+                frame.fill_rectangle(top_left, bar_size, color);
+            },
+            Marker::Triangle(side) => {
+                let half = size * TRIANGLE_SCALE;
+                let reach = size / 2. - 2.;
+                // tip towards `side`, base parallel to it
+                let (tip, base) = match side {
+                    Side::Top => (Vector::new(0., -reach), Vector::new(half, 0.)),
+                    Side::Right => (Vector::new(reach, 0.), Vector::new(0., half)),
+                    Side::Bottom => (Vector::new(0., reach), Vector::new(half, 0.)),
+                    Side::Left => (Vector::new(-reach, 0.), Vector::new(0., half)),
+                };
+                let base_center = center + tip * ((reach - half) / reach);
+                let path = Path::new(|builder| {
+                    builder.move_to(center + tip);
+                    builder.line_to(base_center + base);
+                    builder.line_to(base_center - base);
+                    builder.close();
+                });
+                frame.fill(&path, color);
+            },
+        }
+    }
+
+    fn draw_number(&self, frame: &mut Frame, pos: &Pos, number: usize) {
+        frame.fill_text(Text {
+            content: number.to_string(),
+            position: self.cell_origin(pos) + Vector::new(2., 1.),
+            color: self.palette.text,
+            size: (self.cell_size * NUMBER_SCALE).into(),
+            ..Text::default()
+        });
+    }
+
+    fn draw_letter(&self, frame: &mut Frame, pos: &Pos, ch: char, color: Color) {
+        // centered in the part of the cell below the clue number
+        let number_height = self.cell_size * NUMBER_SCALE;
+        let center = Vector::new(self.cell_size / 2., number_height + (self.cell_size - number_height) / 2.);
+        frame.fill_text(Text {
+            content: ch.to_string(),
+            position: self.cell_origin(pos) + center,
+            color,
+            size: (self.cell_size * TEXT_SCALE).into(),
+            horizontal_alignment: Horizontal::Center,
+            vertical_alignment: Vertical::Center,
+            ..Text::default()
+        });
+    }
+}
+
+impl<'a> canvas::Program<Msg> for GridCanvas<'a> {
+    type State = ();
+
+    fn update(&self, _state: &mut (), event: canvas::Event, bounds: Rectangle, cursor: mouse::Cursor) -> (event::Status, Option<Msg>) {
+        if let canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
+            if let Some(pos) = cursor.position_in(bounds).and_then(|point| self.pos_at(point)) {
+                return (event::Status::Captured, Some(Msg::ClickCell(pos)))
+            }
+        }
+        (event::Status::Ignored, None)
+    }
+
+    fn draw(&self, _state: &(), renderer: &Renderer, _theme: &Theme, bounds: Rectangle, _cursor: mouse::Cursor) -> Vec<Geometry> {
+        vec![self.cache.draw(renderer, bounds.size(), |frame| self.draw_grid(frame))]
+    }
+
+    fn mouse_interaction(&self, _state: &(), bounds: Rectangle, cursor: mouse::Cursor) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+/// Wrong letters (with autocheck) and revealed ones stand out
+fn letter_color(board: &Board, pos: &Pos, palette: &Palette) -> Color {
+    if board.autocheck_wrong(pos) {
+        palette.wrong
+    } else if board.marks_at(pos).revealed {
+        palette.revealed
+    } else {
+        palette.text
+    }
+}